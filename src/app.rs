@@ -1,59 +1,473 @@
+use crate::cli::*;
+use crate::creature::*;
+use crate::editor::*;
+use crate::inspector::*;
+use crate::overlay::*;
+use crate::replay::*;
 use crate::screen::*;
+use crate::sim::*;
+use crate::watch::*;
 use iced::{
-    executor,
-    time::{self, Duration},
-    widget::Canvas,
-    Application, Command, Length,
+    event, executor, keyboard,
+    time::{self, Duration, Instant},
+    widget::{row, Canvas},
+    window, Application, Command, Event, Length, Size, Subscription, Vector,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
 };
 
 #[derive(Debug, Clone)]
 pub enum MyAppMessage {
-    Update,
+    Update(Instant),
+    CycleEdgePolicy,
+    Resized(Size),
+    Zoom { factor: f32, offset: Vector },
+    Pan(Vector),
+    ToggleFollow,
+    SelectNext,
+    ResetCamera,
+    SetTarget(Vector),
+    CursorMoved(Option<Vector>),
+    ToggleFollowCursor,
+    TogglePause,
+    Step,
+    SpeedUp,
+    SlowDown,
+    ToggleOverlay(Overlay),
+    ToggleTailAnimation,
+    ToggleInspector,
+    InspectCircle(usize),
+    Param(ParamChange),
+    // Sent by the sliders, every change until the release is undone at once
+    DragParam(ParamChange),
+    ReleaseParam,
+    CheckFiles,
+    DismissError,
+    SaveSnapshot,
+    LoadSnapshot,
+    Spawn,
+    CloseRequested,
+    ToggleEditor,
+    Edit(Edit),
+    Delete,
+    CyclePattern,
+    ToggleGaze,
+    TogglePalette,
+    CycleTrail,
+    Undo,
+    Redo,
+    ExportCreature,
 }
 pub struct MyApp {
+    // Render rate, the simulation always runs at SIM_DT
     fps: u32,
     screen: Screen,
+    inspector: Inspector,
+    window_size: Size,
+    // Files reloaded when they change, canonical paths so they match the watcher reports
+    creature_path: Option<PathBuf>,
+    behavior_path: Option<PathBuf>,
+    watchers: Vec<DirWatcher>,
+    // Snapshot file used by the save and load hotkeys
+    snapshot_path: PathBuf,
+    // Definition file written by the editor, the builder code goes next to it
+    export_path: PathBuf,
+    paused: bool,
+    // Simulated seconds per real second, one of TIME_SCALES
+    time_scale: f32,
+    // Simulated time not yet consumed by a fixed step, always less than SIM_DT after a frame
+    accumulator: f32,
+    last_frame: Option<Instant>,
 }
 
 impl Application for MyApp {
     type Executor = executor::Default;
     type Message = MyAppMessage;
     type Theme = iced::Theme;
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new(_flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+    fn new(flags: Self::Flags) -> (Self, iced::Command<Self::Message>) {
+        let size = Size::new(flags.width as f32, flags.height as f32);
+        let canonical = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        };
+        let creature_path = canonical(&flags.creature);
+        let behavior_path = canonical(&flags.behavior);
+        let mut watchers: Vec<DirWatcher> = Vec::new();
+        if flags.watch {
+            for path in creature_path.iter().chain(behavior_path.iter()) {
+                let directory = path.parent().unwrap_or(Path::new("."));
+                if !watchers.iter().any(|w| w.directory() == directory) {
+                    watchers.push(DirWatcher::new(directory));
+                }
+            }
+        }
+        let setup = Setup::new(&flags);
+        // A snapshot or recording that fails to load leaves us with new creatures and the error on screen
+        let mut replay = None;
+        let mut loaded = false;
+        let (sim, error) = match (&flags.replay, &flags.load) {
+            (Some(path), _) => match Replayer::load(path) {
+                Ok(replayer) => {
+                    let sim = replayer.simulation();
+                    replay = Some(replayer);
+                    (sim, None)
+                }
+                Err(error) => (setup.simulation(), Some(error.to_string())),
+            },
+            (None, Some(path)) => match Simulation::load(path) {
+                Ok(sim) => {
+                    loaded = true;
+                    (sim, None)
+                }
+                Err(error) => (setup.simulation(), Some(error.to_string())),
+            },
+            (None, None) => (setup.simulation(), None),
+        };
+        let mut screen = Screen::new(sim, flags.layers.clone(), flags.palette());
+        // A snapshot was saved with the world of another window
+        if loaded {
+            screen.resize(size);
+        }
+        if let Some(path) = &flags.record {
+            screen.record(Recorder::new(setup, path, screen.sim()));
+        }
+        if let Some(replay) = replay {
+            screen.replay(replay);
+        }
+        if let Some(message) = error {
+            screen.show_error(message);
+        }
         (
             Self {
-                fps: 30,
-                screen: Screen::new(),
+                fps: flags.fps,
+                screen,
+                inspector: Inspector::default(),
+                window_size: size,
+                creature_path,
+                behavior_path,
+                watchers,
+                snapshot_path: flags.snapshot.clone(),
+                export_path: flags.export.clone(),
+                paused: false,
+                time_scale: 1.0,
+                accumulator: 0.0,
+                last_frame: None,
             },
             Command::none(),
         )
     }
 
     fn title(&self) -> String {
-        String::from("Procedural Animation")
+        match (self.paused, self.time_scale) {
+            (true, _) => String::from("Procedural Animation (paused)"),
+            (false, scale) if scale != 1.0 => format!("Procedural Animation ({}x)", scale),
+            _ => String::from("Procedural Animation"),
+        }
     }
 
-    // Call the update screen function each frame
+    // Each frame, run as many fixed simulation steps as the elapsed (scaled) time asks for
     fn update(&mut self, message: Self::Message) -> iced::Command<Self::Message> {
         match message {
-            MyAppMessage::Update => {
-                self.screen.update();
+            MyAppMessage::Update(now) => {
+                let elapsed = self
+                    .last_frame
+                    .map(|last| (now - last).as_secs_f32())
+                    .unwrap_or(0.0);
+                self.last_frame = Some(now);
+                if !self.paused {
+                    // Don't try to catch up after long stalls, it would only stall more
+                    self.accumulator += elapsed.min(MAX_FRAME_TIME) * self.time_scale;
+                    while self.accumulator >= SIM_DT {
+                        self.screen.update(SIM_DT);
+                        self.accumulator -= SIM_DT;
+                    }
+                }
+                self.screen.render(self.accumulator / SIM_DT, elapsed);
+            }
+            MyAppMessage::TogglePause => {
+                self.paused = !self.paused;
+            }
+            MyAppMessage::Step => {
+                // Stepping only makes sense while paused, show the new step right away
+                self.paused = true;
+                self.screen.step(SIM_DT);
+                self.accumulator = 0.0;
+            }
+            MyAppMessage::SpeedUp => {
+                self.time_scale = Self::next_time_scale(self.time_scale, true);
+            }
+            MyAppMessage::SlowDown => {
+                self.time_scale = Self::next_time_scale(self.time_scale, false);
+            }
+            MyAppMessage::ToggleOverlay(overlay) => {
+                self.screen.toggle_overlay(overlay);
+            }
+            MyAppMessage::ToggleTailAnimation => {
+                self.screen.toggle_tail_animation();
+            }
+            MyAppMessage::ToggleInspector => {
+                self.inspector.visible = !self.inspector.visible;
+                self.screen.resize(self.canvas_size());
+            }
+            MyAppMessage::InspectCircle(i) => {
+                self.inspector.circle = i;
+            }
+            MyAppMessage::Param(change) => {
+                self.screen.apply_param(&change);
+            }
+            MyAppMessage::DragParam(change) => {
+                self.screen.drag_param(&change);
+            }
+            MyAppMessage::ReleaseParam => {
+                self.screen.end_drag();
+            }
+            MyAppMessage::CheckFiles => {
+                let changed: Vec<PathBuf> =
+                    self.watchers.iter_mut().flat_map(|w| w.poll()).collect();
+                for path in changed {
+                    self.reload(&path);
+                }
+            }
+            MyAppMessage::DismissError => {
+                self.screen.dismiss_error();
+            }
+            MyAppMessage::SaveSnapshot => match self.screen.sim().save(&self.snapshot_path) {
+                Ok(()) => println!("Saved snapshot '{}'", self.snapshot_path.display()),
+                Err(error) => self.screen.show_error(error.to_string()),
+            },
+            MyAppMessage::ToggleEditor => {
+                self.screen.toggle_editor();
+            }
+            MyAppMessage::Edit(edit) => {
+                self.screen.edit(&edit);
+            }
+            MyAppMessage::Delete => {
+                self.screen.delete();
+            }
+            MyAppMessage::CyclePattern => {
+                self.screen.cycle_pattern();
+            }
+            MyAppMessage::ToggleGaze => {
+                self.screen.toggle_gaze();
+            }
+            MyAppMessage::TogglePalette => {
+                self.screen.toggle_palette();
+            }
+            MyAppMessage::CycleTrail => {
+                self.screen.cycle_trail();
+            }
+            MyAppMessage::Undo => {
+                self.screen.undo();
+            }
+            MyAppMessage::Redo => {
+                self.screen.redo();
+            }
+            MyAppMessage::ExportCreature => {
+                self.screen.export(&self.export_path);
+            }
+            MyAppMessage::Spawn => {
+                self.screen.spawn();
+            }
+            MyAppMessage::CloseRequested => {
+                // The recording is written once the session is over
+                self.screen.save_recording();
+                return window::close(window::Id::MAIN);
+            }
+            MyAppMessage::LoadSnapshot => match Simulation::load(&self.snapshot_path) {
+                Ok(sim) => {
+                    // Start the restored simulation on a clean step
+                    self.screen.replace_sim(sim, self.canvas_size());
+                    self.accumulator = 0.0;
+                    self.screen.dismiss_error();
+                }
+                Err(error) => self.screen.show_error(error.to_string()),
+            },
+            MyAppMessage::CycleEdgePolicy => {
+                self.screen.cycle_edge_policy();
+            }
+            MyAppMessage::Resized(size) => {
+                self.window_size = size;
+                self.screen.resize(self.canvas_size());
+            }
+            MyAppMessage::Zoom { factor, offset } => {
+                self.screen.zoom(factor, offset);
+            }
+            MyAppMessage::Pan(delta) => {
+                self.screen.pan(delta);
+            }
+            MyAppMessage::ToggleFollow => {
+                self.screen.toggle_follow();
+            }
+            MyAppMessage::SelectNext => {
+                self.screen.select_next();
+            }
+            MyAppMessage::ResetCamera => {
+                self.screen.reset_camera();
+            }
+            MyAppMessage::SetTarget(offset) => {
+                self.screen.set_target(offset);
+            }
+            MyAppMessage::CursorMoved(offset) => {
+                self.screen.move_cursor(offset);
+            }
+            MyAppMessage::ToggleFollowCursor => {
+                self.screen.toggle_follow_cursor();
             }
         }
         Command::none()
     }
 
-    fn view(&self) -> iced::Element<Self::Message> {
-        Canvas::new(&self.screen)
+    fn theme(&self) -> Self::Theme {
+        self.screen.palette().theme()
+    }
+
+    fn view(&self) -> iced::Element<'_, Self::Message> {
+        let canvas = Canvas::new(&self.screen)
             .width(Length::Fill)
-            .height(Length::Fill)
-            .into()
+            .height(Length::Fill);
+        if self.inspector.visible {
+            row![canvas, self.inspector.view(self.screen.selected_snake())].into()
+        } else {
+            canvas.into()
+        }
     }
 
     // Frame every 1/fps seconds
     fn subscription(&self) -> iced::Subscription<Self::Message> {
-        time::every(Duration::from_millis((1000 / self.fps) as u64)).map(|_| MyAppMessage::Update)
+        Subscription::batch([
+            time::every(Duration::from_millis((1000 / self.fps) as u64)).map(MyAppMessage::Update),
+            keyboard::on_key_press(Self::hotkey),
+            event::listen_with(Self::window_event),
+            if self.watchers.is_empty() {
+                Subscription::none()
+            } else {
+                time::every(Duration::from_millis(500)).map(|_| MyAppMessage::CheckFiles)
+            },
+        ])
+    }
+}
+
+// Length of a simulation step in seconds
+pub const SIM_DT: f32 = 1.0 / 60.0;
+// Longest real time a single frame is allowed to simulate
+const MAX_FRAME_TIME: f32 = 0.25;
+// Time scales stepped through with + and -, always passing through 1x
+const TIME_SCALES: [f32; 6] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0];
+
+impl MyApp {
+    // Next faster or slower time scale, staying at the ends of the list
+    fn next_time_scale(scale: f32, faster: bool) -> f32 {
+        let current = TIME_SCALES
+            .iter()
+            .position(|s| *s >= scale)
+            .unwrap_or(TIME_SCALES.len() - 1);
+        let next = match faster {
+            true => (current + 1).min(TIME_SCALES.len() - 1),
+            false => current.saturating_sub(1),
+        };
+        TIME_SCALES[next]
+    }
+
+    // Reload a watched file, errors are shown on screen and the old definition is kept
+    fn reload(&mut self, path: &Path) {
+        if self.creature_path.as_deref() == Some(path) {
+            match CreatureDef::load(path) {
+                Ok(def) => {
+                    self.screen.reload_creature(def);
+                    self.screen.dismiss_error();
+                }
+                Err(error) => self.screen.show_error(error.to_string()),
+            }
+        } else if self.behavior_path.as_deref() == Some(path) {
+            match BehaviorDef::load(path) {
+                Ok(def) => {
+                    self.screen.reload_behavior(def);
+                    self.screen.dismiss_error();
+                }
+                Err(error) => self.screen.show_error(error.to_string()),
+            }
+        }
+    }
+
+    // The inspector panel takes space from the right side of the canvas
+    fn canvas_size(&self) -> Size {
+        if self.inspector.visible {
+            Size::new(
+                (self.window_size.width - PANEL_WIDTH).max(0.0),
+                self.window_size.height,
+            )
+        } else {
+            self.window_size
+        }
+    }
+
+    // Map key presses to app messages
+    fn hotkey(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<MyAppMessage> {
+        // Ctrl (Cmd on macOS) shortcuts for the snapshot file
+        if modifiers.command() {
+            return match key.as_ref() {
+                keyboard::Key::Character("s") => Some(MyAppMessage::SaveSnapshot),
+                keyboard::Key::Character("o") => Some(MyAppMessage::LoadSnapshot),
+                keyboard::Key::Character("e") => Some(MyAppMessage::ExportCreature),
+                // Shift might turn the key into an upper case letter
+                keyboard::Key::Character("z") | keyboard::Key::Character("Z")
+                    if modifiers.shift() =>
+                {
+                    Some(MyAppMessage::Redo)
+                }
+                keyboard::Key::Character("z") => Some(MyAppMessage::Undo),
+                keyboard::Key::Character("y") => Some(MyAppMessage::Redo),
+                _ => None,
+            };
+        }
+        match key.as_ref() {
+            keyboard::Key::Named(keyboard::key::Named::Space) => Some(MyAppMessage::TogglePause),
+            keyboard::Key::Character(".") => Some(MyAppMessage::Step),
+            keyboard::Key::Character("+") | keyboard::Key::Character("=") => {
+                Some(MyAppMessage::SpeedUp)
+            }
+            keyboard::Key::Character("-") => Some(MyAppMessage::SlowDown),
+            keyboard::Key::Character("1") => Some(MyAppMessage::ToggleOverlay(Overlay::Circles)),
+            keyboard::Key::Character("2") => Some(MyAppMessage::ToggleOverlay(Overlay::Centers)),
+            keyboard::Key::Character("3") => Some(MyAppMessage::ToggleOverlay(Overlay::Directions)),
+            keyboard::Key::Character("4") => {
+                Some(MyAppMessage::ToggleOverlay(Overlay::OutlinePoints))
+            }
+            keyboard::Key::Character("5") => Some(MyAppMessage::ToggleOverlay(Overlay::VisionCone)),
+            keyboard::Key::Character("6") => Some(MyAppMessage::ToggleOverlay(Overlay::BlindSpots)),
+            keyboard::Key::Character("7") => Some(MyAppMessage::ToggleOverlay(Overlay::Action)),
+            keyboard::Key::Character("n") => Some(MyAppMessage::Spawn),
+            keyboard::Key::Character("e") => Some(MyAppMessage::ToggleEditor),
+            keyboard::Key::Character("p") => Some(MyAppMessage::CyclePattern),
+            keyboard::Key::Character("g") => Some(MyAppMessage::ToggleGaze),
+            keyboard::Key::Character("l") => Some(MyAppMessage::TogglePalette),
+            keyboard::Key::Character("r") => Some(MyAppMessage::CycleTrail),
+            keyboard::Key::Named(keyboard::key::Named::Delete)
+            | keyboard::Key::Named(keyboard::key::Named::Backspace) => Some(MyAppMessage::Delete),
+            keyboard::Key::Character("t") => Some(MyAppMessage::ToggleTailAnimation),
+            keyboard::Key::Character("i") => Some(MyAppMessage::ToggleInspector),
+            keyboard::Key::Named(keyboard::key::Named::Escape) => Some(MyAppMessage::DismissError),
+            keyboard::Key::Character("b") => Some(MyAppMessage::CycleEdgePolicy),
+            keyboard::Key::Character("f") => Some(MyAppMessage::ToggleFollow),
+            keyboard::Key::Character("c") => Some(MyAppMessage::ResetCamera),
+            keyboard::Key::Character("m") => Some(MyAppMessage::ToggleFollowCursor),
+            keyboard::Key::Named(keyboard::key::Named::Tab) => Some(MyAppMessage::SelectNext),
+            _ => None,
+        }
+    }
+
+    // The canvas size follows the window size
+    fn window_event(event: Event, _status: event::Status) -> Option<MyAppMessage> {
+        match event {
+            Event::Window(_, window::Event::Resized { width, height }) => Some(
+                MyAppMessage::Resized(Size::new(width as f32, height as f32)),
+            ),
+            Event::Window(_, window::Event::CloseRequested) => Some(MyAppMessage::CloseRequested),
+            _ => None,
+        }
     }
 }
@@ -0,0 +1,306 @@
+use crate::chain::*;
+use crate::circle::*;
+use iced::{widget::canvas::path::Builder, Point, Vector};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// Shape of an appendage, lengths in pixels and angles in radians
+// rake leans the appendage back towards the tail, on whichever side of the body it is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AppendageKind {
+    // Fin flapping by up to flap around its rest angle, faster and wider while the creature turns
+    Fin {
+        length: f32,
+        width: f32,
+        rake: f32,
+        flap: f32,
+    },
+    // Rigid pointed spike
+    Spike {
+        length: f32,
+        width: f32,
+        rake: f32,
+    },
+    // Thin feeler whose tip is pulled towards its rest position by a damped spring
+    Antenna {
+        length: f32,
+        stiffness: f32,
+        damping: f32,
+    },
+}
+
+// Motion of an appendage between steps, the tip of an antenna is in world coordinates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppendageState {
+    phase: f32,
+    amplitude: f32,
+    #[serde(with = "crate::persist::vector")]
+    tip: Vector,
+    #[serde(with = "crate::persist::vector")]
+    velocity: Vector,
+    // The tip is placed at its rest position on the first update
+    ready: bool,
+}
+
+// Decoration attached to the rim of a circle, at an angle from its direction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Appendage {
+    pub circle: usize,
+    pub angle: f32,
+    pub kind: AppendageKind,
+    #[serde(default)]
+    state: AppendageState,
+}
+
+impl AppendageKind {
+    // Creature files write the angles in degrees
+    pub fn to_radians(&self) -> Self {
+        self.map_angles(f32::to_radians)
+    }
+
+    pub fn to_degrees(&self) -> Self {
+        self.map_angles(f32::to_degrees)
+    }
+
+    fn map_angles(&self, convert: impl Fn(f32) -> f32) -> Self {
+        match *self {
+            Self::Fin {
+                length,
+                width,
+                rake,
+                flap,
+            } => Self::Fin {
+                length,
+                width,
+                rake: convert(rake),
+                flap: convert(flap),
+            },
+            Self::Spike {
+                length,
+                width,
+                rake,
+            } => Self::Spike {
+                length,
+                width,
+                rake: convert(rake),
+            },
+            Self::Antenna { .. } => self.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        match *self {
+            Self::Fin {
+                length,
+                width,
+                rake,
+                flap,
+            } if !positive(length)
+                || !positive(width)
+                || !rake.is_finite()
+                || !flap.is_finite() =>
+            {
+                Err(String::from(
+                    "fin length and width must be positive and its angles finite numbers",
+                ))
+            }
+            Self::Spike {
+                length,
+                width,
+                rake,
+            } if !positive(length) || !positive(width) || !rake.is_finite() => Err(String::from(
+                "spike length and width must be positive and rake a finite number",
+            )),
+            Self::Antenna {
+                length,
+                stiffness,
+                damping,
+            } if !positive(length)
+                || !positive(stiffness)
+                || !damping.is_finite()
+                || damping < 0.0 =>
+            {
+                Err(String::from(
+                    "antenna length and stiffness must be positive and damping at least 0",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Appendage {
+    // Turn rate in radians per second at which fins flap the widest
+    const FULL_TURN: f32 = 1.0;
+    // Flapping speed in radians per second, when going straight and when turning at FULL_TURN
+    const FLAP_SPEED: (f32, f32) = (3.0, 9.0);
+    // Fraction of the flap kept while going straight
+    const IDLE_FLAP: f32 = 0.2;
+
+    pub fn new(circle: usize, angle: f32, kind: AppendageKind) -> Self {
+        Self {
+            circle,
+            angle,
+            kind,
+            state: AppendageState::default(),
+        }
+    }
+
+    pub fn fin(circle: usize, angle: f32, length: f32, width: f32) -> Self {
+        Self::new(
+            circle,
+            angle,
+            AppendageKind::Fin {
+                length,
+                width,
+                rake: PI / 4.0,
+                flap: PI / 8.0,
+            },
+        )
+    }
+
+    pub fn spike(circle: usize, angle: f32, length: f32, width: f32) -> Self {
+        Self::new(
+            circle,
+            angle,
+            AppendageKind::Spike {
+                length,
+                width,
+                rake: PI / 6.0,
+            },
+        )
+    }
+
+    pub fn antenna(circle: usize, angle: f32, length: f32) -> Self {
+        Self::new(
+            circle,
+            angle,
+            AppendageKind::Antenna {
+                length,
+                stiffness: 120.0,
+                damping: 12.0,
+            },
+        )
+    }
+
+    // Point of the rim the appendage grows from and the unit vector pointing out of the circle there
+    fn root(&self, circle: &Circle) -> (Vector, Vector) {
+        let outward = Chain::rotate_vector(circle.direction, self.angle);
+        (circle.point_on_circle(outward), outward)
+    }
+
+    // Rotation that leans towards the tail: angles below PI are on one side of the body, the others on the other
+    fn backwards(&self, angle: f32) -> f32 {
+        match self.angle.rem_euclid(2.0 * PI) < PI {
+            true => angle,
+            false => -angle,
+        }
+    }
+
+    // Advance the flapping and the springs, turn is the turn rate of the head in radians per second
+    pub fn update(&mut self, circle: &Circle, turn: f32, dt: f32) {
+        let (root, outward) = self.root(circle);
+        let state = &mut self.state;
+        match self.kind {
+            AppendageKind::Fin { .. } => {
+                let turning = (turn.abs() / Self::FULL_TURN).min(1.0);
+                let (slow, fast) = Self::FLAP_SPEED;
+                state.phase = (state.phase + (slow + (fast - slow) * turning) * dt) % (2.0 * PI);
+                let target = Self::IDLE_FLAP + (1.0 - Self::IDLE_FLAP) * turning;
+                state.amplitude += (target - state.amplitude) * (4.0 * dt).min(1.0);
+            }
+            AppendageKind::Spike { .. } => {}
+            AppendageKind::Antenna {
+                length,
+                stiffness,
+                damping,
+            } => {
+                let rest = root + outward * length;
+                // Start at rest, and again after the body jumped (e.g. wrapping around the world)
+                if !state.ready || Chain::vector_length(state.tip - root) > 2.0 * length {
+                    state.tip = rest;
+                    state.velocity = Vector::new(0.0, 0.0);
+                    state.ready = true;
+                    return;
+                }
+                let acceleration = (rest - state.tip) * stiffness - state.velocity * damping;
+                state.velocity = state.velocity + acceleration * dt;
+                state.tip = state.tip + state.velocity * dt;
+                // The antenna doesn't stretch
+                let reach = state.tip - root;
+                let distance = Chain::vector_length(reach);
+                if distance > f32::EPSILON {
+                    state.tip = root + reach * (length / distance);
+                }
+            }
+        }
+    }
+
+    // Outline of a fin or a spike, antennae are drawn as lines by antenna_path
+    pub fn shape_path(&self, circle: &Circle, frame_center: Point, builder: &mut Builder) {
+        let (root, outward) = self.root(circle);
+        let (length, width, lean) = match self.kind {
+            AppendageKind::Fin {
+                length,
+                width,
+                rake,
+                flap,
+            } => (
+                length,
+                width,
+                rake + flap * self.state.amplitude * self.state.phase.sin(),
+            ),
+            AppendageKind::Spike {
+                length,
+                width,
+                rake,
+            } => (length, width, rake),
+            AppendageKind::Antenna { .. } => return,
+        };
+        let tip = root + Chain::rotate_vector(outward, self.backwards(lean)) * length;
+        // Both sides of the base lie on the rim
+        let half = (width / 2.0 / circle.radius.max(f32::EPSILON)).min(PI / 2.0);
+        let base = |side: f32| {
+            circle.point_on_circle(Chain::rotate_vector(circle.direction, self.angle + side))
+        };
+        let (left, right) = (base(-half), base(half));
+        builder.move_to(frame_center + left);
+        match self.kind {
+            // Fins bulge out like leaves
+            AppendageKind::Fin { .. } => {
+                builder.quadratic_curve_to(
+                    frame_center + left + (tip - root) * 0.6,
+                    frame_center + tip,
+                );
+                builder.quadratic_curve_to(
+                    frame_center + right + (tip - root) * 0.6,
+                    frame_center + right,
+                );
+            }
+            _ => {
+                builder.line_to(frame_center + tip);
+                builder.line_to(frame_center + right);
+            }
+        }
+        builder.close();
+    }
+
+    // Curve of an antenna, leaving the body straight out and bending towards the lagging tip
+    pub fn antenna_path(&self, circle: &Circle, frame_center: Point, builder: &mut Builder) {
+        let AppendageKind::Antenna { length, .. } = self.kind else {
+            return;
+        };
+        let (root, outward) = self.root(circle);
+        let tip = match self.state.ready {
+            true => self.state.tip,
+            false => root + outward * length,
+        };
+        builder.move_to(frame_center + root);
+        builder.quadratic_curve_to(
+            frame_center + root + outward * (length * 0.5),
+            frame_center + tip,
+        );
+        builder.circle(frame_center + tip, 2.5);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::app::*;
+use crate::chain::*;
+use crate::cli::*;
+use crate::replay::*;
+use iced::{Point, Vector};
+use std::{
+    error::Error,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+// Time the outline paths of every creature, built the way draw_outline used to (two outline_path
+// calls, every point computed twice) and from one contour buffer refilled every frame
+pub fn run(flags: &Flags) -> Result<(), Box<dyn Error>> {
+    let mut sim = Setup::new(flags).simulation();
+    let mut buffers: Vec<Vec<Vector>> = vec![Vec::new(); sim.snakes.len()];
+    let (mut before, mut after) = (Duration::ZERO, Duration::ZERO);
+
+    for _ in 0..flags.steps {
+        sim.update(SIM_DT);
+        for snake in sim.snakes.iter_mut() {
+            snake.interpolate(0.5);
+        }
+
+        let start = Instant::now();
+        for snake in sim.snakes.iter() {
+            black_box(snake.display.outline_path(Point::ORIGIN));
+            black_box(snake.display.outline_path(Point::ORIGIN));
+        }
+        before += start.elapsed();
+
+        let start = Instant::now();
+        for (snake, buffer) in sim.snakes.iter().zip(buffers.iter_mut()) {
+            snake.display.outline_points_into(buffer);
+            black_box(Chain::polygon_path(buffer, Point::ORIGIN));
+        }
+        after += start.elapsed();
+    }
+
+    let circles: usize = sim.snakes.iter().map(|s| s.chain.circles.len()).sum();
+    let frames = flags.steps.max(1) as f64;
+    let per_frame = |total: Duration| total.as_secs_f64() * 1e6 / frames;
+    println!(
+        "Outlines of {} creatures ({} circles) over {} frames",
+        sim.snakes.len(),
+        circles,
+        flags.steps
+    );
+    println!(
+        "  two outline_path calls: {:>9.1} us/frame",
+        per_frame(before)
+    );
+    println!(
+        "  one reused buffer:      {:>9.1} us/frame",
+        per_frame(after)
+    );
+    println!(
+        "  {:.2}x faster",
+        before.as_secs_f64() / after.as_secs_f64().max(f64::EPSILON)
+    );
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use iced::{widget::canvas::Frame, Point, Vector};
+
+// Camera mapping world coordinates to canvas coordinates
+// Offsets are measured from the center of the canvas, so the camera doesn't need the canvas size
+pub struct Camera {
+    // World point shown in the center of the canvas
+    pub center: Vector,
+    pub zoom: f32,
+    // Smoothly track the head of the selected creature
+    pub follow: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            center: Vector::new(0.0, 0.0),
+            zoom: 1.0,
+            follow: false,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Camera {
+    const MIN_ZOOM: f32 = 0.1;
+    const MAX_ZOOM: f32 = 10.0;
+    // How fast the camera catches up with the followed point, per second
+    const FOLLOW_RATE: f32 = 3.0;
+
+    // Convert an offset from the canvas center into a world position
+    pub fn to_world(&self, offset: Vector) -> Vector {
+        self.center + offset * (1.0 / self.zoom)
+    }
+
+    // Convert a world position into an offset from the canvas center
+    pub fn to_screen(&self, position: Vector) -> Vector {
+        (position - self.center) * self.zoom
+    }
+
+    // Multiply the zoom while keeping the world point under the cursor in place
+    pub fn zoom_at(&mut self, factor: f32, offset: Vector) {
+        let before = self.to_world(offset);
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let after = self.to_world(offset);
+        self.center = self.center + (before - after);
+    }
+
+    // Move the camera by a distance measured on the canvas, dragging stops following
+    pub fn pan(&mut self, delta: Vector) {
+        self.center = self.center - delta * (1.0 / self.zoom);
+        self.follow = false;
+    }
+
+    // Move the camera closer to the target, dt seconds after the last call
+    pub fn track(&mut self, target: Vector, dt: f32) {
+        let smoothing = 1.0 - (-Self::FOLLOW_RATE * dt).exp();
+        self.center = self.center + (target - self.center) * smoothing;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Transform the frame so that paths built in world coordinates (around Point::ORIGIN) land in the right place
+    pub fn apply(&self, frame: &mut Frame) {
+        frame.translate(frame.center() - Point::ORIGIN);
+        frame.scale(self.zoom);
+        frame.translate(self.center * (-1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vector, b: Vector) -> bool {
+        (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3
+    }
+
+    #[test]
+    fn world_and_screen_round_trip() {
+        let camera = Camera {
+            center: Vector::new(30.0, -40.0),
+            zoom: 2.5,
+            follow: false,
+        };
+        for point in [
+            Vector::new(0.0, 0.0),
+            Vector::new(123.0, -45.5),
+            Vector::new(-800.0, 600.0),
+        ] {
+            assert!(close(camera.to_world(camera.to_screen(point)), point));
+            assert!(close(camera.to_screen(camera.to_world(point)), point));
+        }
+        assert_eq!(camera.to_screen(camera.center), Vector::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let mut camera = Camera::default();
+        let cursor = Vector::new(150.0, -90.0);
+        let before = camera.to_world(cursor);
+        camera.zoom_at(3.0, cursor);
+        assert!(close(camera.to_world(cursor), before));
+        camera.zoom_at(1000.0, cursor);
+        assert_eq!(camera.zoom, Camera::MAX_ZOOM);
+        assert!(close(camera.to_world(cursor), before));
+    }
+
+    #[test]
+    fn pan_follows_the_drag_at_any_zoom() {
+        let mut camera = Camera {
+            zoom: 2.0,
+            follow: true,
+            ..Camera::default()
+        };
+        let grabbed = camera.to_world(Vector::new(10.0, 10.0));
+        camera.pan(Vector::new(40.0, -20.0));
+        assert!(close(camera.to_world(Vector::new(50.0, -10.0)), grabbed));
+        assert!(!camera.follow);
+    }
+}
@@ -1,18 +1,25 @@
-use std::{cmp::min, f32::consts::PI};
+use std::f32::consts::PI;
 
+use crate::appendage::*;
 use crate::circle::*;
 use iced::{widget::canvas::Path, Point, Vector};
+use serde::{Deserialize, Serialize};
 
 // Using angles in radians: 360 degrees == 2PI radians
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Chain {
     pub circles: Vec<Circle>,
     pub outlines: Vec<Vec<f32>>,
+    // Fins, spikes and antennae attached to the circles
+    #[serde(default)]
+    pub appendages: Vec<Appendage>,
 }
 
 // Builder pattern for chain struct
 pub struct ChainBuilder {
     circles: Vec<Circle>,
     outlines: Vec<Vec<f32>>,
+    appendages: Vec<Appendage>,
 }
 
 #[allow(dead_code)]
@@ -28,8 +35,8 @@ impl ChainBuilder {
 
     // Function that sets the offset from the frontier for each circle
     pub fn circles_offsets(&mut self, offset_array: Vec<f32>) -> &mut Self {
-        for i in 0..min(offset_array.len(), self.circles.len()) {
-            self.circles[i].offset = offset_array[i];
+        for (circle, offset) in self.circles.iter_mut().zip(offset_array) {
+            circle.offset = offset;
         }
         self
     }
@@ -68,18 +75,20 @@ impl ChainBuilder {
             .map(|a| {
                 a.iter()
                     .filter(|f| !f.is_nan())
-                    .map(|f| *f)
+                    .copied()
                     .map(|f| (f + PI * 2.0) % (PI * 2.0))
                     .collect()
             })
             .collect();
-        let asize = angles.len();
-        for i in 0..min(size, asize) {
+        for (i, circle_angles) in angles.iter().enumerate().take(size) {
             // Filter the angles into the two halves
-            if !angles[i].is_empty() {
-                self.outlines[i] = angles[i].iter().filter(|f| **f < PI).map(|f| *f).collect();
-                self.outlines[size + i] =
-                    angles[i].iter().filter(|f| **f >= PI).map(|f| *f).collect();
+            if !circle_angles.is_empty() {
+                self.outlines[i] = circle_angles.iter().filter(|f| **f < PI).copied().collect();
+                self.outlines[size + i] = circle_angles
+                    .iter()
+                    .filter(|f| **f >= PI)
+                    .copied()
+                    .collect();
             }
             // Sort the angles for each half
             self.outlines[i].sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
@@ -89,16 +98,24 @@ impl ChainBuilder {
         self
     }
 
+    // Attach decorations to the circles, appendages of missing circles are ignored
+    pub fn appendages(&mut self, appendages: Vec<Appendage>) -> &mut Self {
+        self.appendages = appendages;
+        self
+    }
+
     // Return the built chain
     pub fn build(&mut self) -> Chain {
         Chain {
             circles: self.circles.clone(),
             outlines: self.outlines.clone(),
+            appendages: self.appendages.clone(),
         }
     }
 }
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Orientation {
     LEFT,
     CENTER,
@@ -106,10 +123,12 @@ pub enum Orientation {
 }
 
 impl Chain {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> ChainBuilder {
         ChainBuilder {
             circles: vec![Circle::default()],
             outlines: Vec::new(),
+            appendages: Vec::new(),
         }
     }
 
@@ -137,6 +156,64 @@ impl Chain {
         }
     }
 
+    // Move every circle of the chain by the same offset
+    pub fn translate(&mut self, offset: Vector) {
+        for circle in self.circles.iter_mut() {
+            circle.position = circle.position + offset;
+        }
+    }
+
+    // Blend two states of the same chain into this one, alpha goes from 0 (previous) to 1 (current)
+    // Circles that jumped too far in one step (e.g. wrapping around the world) are not blended
+    pub fn interpolate(&mut self, previous: &Chain, current: &Chain, alpha: f32) {
+        const MAX_BLEND_DISTANCE: f32 = 100.0;
+        self.circles.clone_from(&current.circles);
+        self.outlines.clone_from(&current.outlines);
+        self.appendages.clone_from(&current.appendages);
+        for (circle, old) in self.circles.iter_mut().zip(previous.circles.iter()) {
+            if Self::vector_length(circle.position - old.position) > MAX_BLEND_DISTANCE {
+                continue;
+            }
+            circle.position = old.position + (circle.position - old.position) * alpha;
+            let direction = old.direction + (circle.direction - old.direction) * alpha;
+            if Self::vector_length(direction) > f32::EPSILON {
+                circle.direction = direction;
+                circle.normalize_direction();
+            }
+        }
+    }
+
+    // Move the appendages along with the circles, turn is the turn rate of the head in radians per second
+    pub fn update_appendages(&mut self, turn: f32, dt: f32) {
+        for appendage in self.appendages.iter_mut() {
+            if let Some(circle) = self.circles.get(appendage.circle) {
+                appendage.update(circle, turn, dt);
+            }
+        }
+    }
+
+    // Filled shapes of the fins and spikes
+    pub fn appendages_path(&self, frame_center: Point) -> Path {
+        Path::new(|builder| {
+            for appendage in self.appendages.iter() {
+                if let Some(circle) = self.circles.get(appendage.circle) {
+                    appendage.shape_path(circle, frame_center, builder);
+                }
+            }
+        })
+    }
+
+    // Stroked lines of the antennae
+    pub fn antennae_path(&self, frame_center: Point) -> Path {
+        Path::new(|builder| {
+            for appendage in self.appendages.iter() {
+                if let Some(circle) = self.circles.get(appendage.circle) {
+                    appendage.antenna_path(circle, frame_center, builder);
+                }
+            }
+        })
+    }
+
     // Calculate the length of a 2D vector
     pub fn vector_length(v: Vector) -> f32 {
         (v.x.powf(2.0) + v.y.powf(2.0)).sqrt()
@@ -205,39 +282,81 @@ impl Chain {
         })
     }
 
+    // Contour points of a single circle, its left half then its right half
+    pub fn circle_outline_points(&self, i: usize) -> Vec<Vector> {
+        let n = self.circles.len();
+        self.outlines[i]
+            .iter()
+            .chain(self.outlines[n + i].iter())
+            .map(|ang| {
+                self.circles[i]
+                    .point_on_circle(Self::rotate_vector(self.circles[i].direction, *ang))
+            })
+            .collect()
+    }
+
+    // Points of the contour in drawing order: the left halves from head to tail, then the right halves from tail to head
+    pub fn outline_points(&self) -> Vec<Vector> {
+        let mut points = Vec::new();
+        self.outline_points_into(&mut points);
+        points
+    }
+
+    // Same as outline_points, reusing the memory of a buffer from an earlier call
+    pub fn outline_points_into(&self, points: &mut Vec<Vector>) {
+        let n = self.circles.len();
+        points.clear();
+        for i in 0..n {
+            for ang in self.outlines[i].iter() {
+                points.push(
+                    self.circles[i]
+                        .point_on_circle(Self::rotate_vector(self.circles[i].direction, *ang)),
+                );
+            }
+        }
+        for i in (0..n).rev() {
+            for ang in self.outlines[i + n].iter() {
+                points.push(
+                    self.circles[i]
+                        .point_on_circle(Self::rotate_vector(self.circles[i].direction, *ang)),
+                );
+            }
+        }
+    }
+
+    // Circle of every point of outline_points, in the same order
+    pub fn outline_circles(&self) -> impl Iterator<Item = usize> + '_ {
+        let n = self.circles.len();
+        let left = (0..n).flat_map(|i| std::iter::repeat_n(i, self.outlines[i].len()));
+        let right = (0..n)
+            .rev()
+            .flat_map(move |i| std::iter::repeat_n(i, self.outlines[i + n].len()));
+        left.chain(right)
+    }
+
+    // Points inside the contour, one per circle from circle from to circle to, at v times the radius
+    // from the center, v goes from -1 (left edge, the side of the first outline half) to 1 (right edge)
+    pub fn inset_points_into(&self, from: usize, to: usize, v: f32, points: &mut Vec<Vector>) {
+        points.clear();
+        for circle in self.circles.iter().take(to + 1).skip(from) {
+            let side = Self::rotate_vector(circle.direction, 3.0 * PI / 2.0);
+            points.push(circle.position + side * (circle.radius * v));
+        }
+    }
+
     pub fn outline_path(&self, frame_center: Point) -> Path {
+        Self::polygon_path(&self.outline_points(), frame_center)
+    }
+
+    // Closed path through contour points, e.g. the ones of outline_points
+    pub fn polygon_path(points: &[Vector], frame_center: Point) -> Path {
         Path::new(|builder| {
-            let n = self.circles.len();
             // Start the path at the last point of the right half of the first line
-            builder.move_to(
-                frame_center
-                    + self.circles[0].point_on_circle(Self::rotate_vector(
-                        self.circles[0].direction,
-                        *self.outlines[n].last().unwrap_or(&0.0),
-                    )),
-            );
-
-            for i in 0..n {
-                for ang in self.outlines[i].iter() {
-                    builder.line_to(
-                        frame_center
-                            + self.circles[i].point_on_circle(Self::rotate_vector(
-                                self.circles[i].direction,
-                                *ang,
-                            )),
-                    );
-                }
+            if let Some(last) = points.last() {
+                builder.move_to(frame_center + *last);
             }
-            for i in (0..n).rev() {
-                for ang in self.outlines[i + n].iter() {
-                    builder.line_to(
-                        frame_center
-                            + self.circles[i].point_on_circle(Self::rotate_vector(
-                                self.circles[i].direction,
-                                *ang,
-                            )),
-                    );
-                }
+            for point in points.iter() {
+                builder.line_to(frame_center + *point);
             }
             builder.close();
         })
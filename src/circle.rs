@@ -1,8 +1,13 @@
 use iced::{widget::canvas::Path, Point, Vector};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
 pub struct Circle {
     pub radius: f32,
     pub offset: f32,
+    #[serde(with = "crate::persist::vector")]
     pub position: Vector,
+    #[serde(with = "crate::persist::vector")]
     pub direction: Vector,
     pub show_center: bool,
 }
@@ -24,8 +29,8 @@ impl Clone for Circle {
         Self {
             radius: self.radius,
             offset: self.offset,
-            position: self.position.clone(),
-            direction: self.direction.clone(),
+            position: self.position,
+            direction: self.direction,
             show_center: self.show_center,
         }
     }
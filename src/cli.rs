@@ -0,0 +1,246 @@
+use crate::creature::*;
+use crate::layer::*;
+use crate::palette::*;
+use crate::snake::*;
+use clap::{builder::RangedU64ValueParser, error::ErrorKind, CommandFactory, Parser};
+use std::path::{Path, PathBuf};
+
+// Command line configuration, passed to the app as its Flags
+#[derive(Debug, Clone, Parser)]
+#[command(version, about = "Procedural animation of chain creatures")]
+pub struct Flags {
+    /// Window width in logical pixels
+    #[arg(long, default_value_t = 1024, value_parser = clap::value_parser!(u32).range(200..=8192))]
+    pub width: u32,
+
+    /// Window height in logical pixels
+    #[arg(long, default_value_t = 768, value_parser = clap::value_parser!(u32).range(200..=8192))]
+    pub height: u32,
+
+    /// Frames drawn per second, the simulation itself always runs at a fixed rate
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..=240))]
+    pub fps: u32,
+
+    /// Seed for the random generator, a random one is picked if missing
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Body shape of the spawned creatures
+    #[arg(long, value_enum, default_value_t = Preset::Slick)]
+    pub preset: Preset,
+
+    /// Creature definition file (RON) used instead of the preset
+    #[arg(long, value_name = "PATH", conflicts_with = "preset")]
+    pub creature: Option<PathBuf>,
+
+    /// Loaded from the creature file
+    #[arg(skip)]
+    pub creature_def: Option<CreatureDef>,
+
+    /// Behavior file (RON) overriding the speeds and angles of every creature
+    #[arg(long, value_name = "PATH")]
+    pub behavior: Option<PathBuf>,
+
+    /// Loaded from the behavior file
+    #[arg(skip)]
+    pub behavior_def: Option<BehaviorDef>,
+
+    /// Reload the creature and behavior files when they change on disk
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Number of creatures spawned at startup
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..=64))]
+    pub creatures: usize,
+
+    /// Run the simulation without opening a window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Time building the outlines instead of writing results, running --steps frames (headless mode)
+    #[arg(
+        long,
+        requires = "headless",
+        conflicts_with_all = ["load", "save", "record", "replay", "trace"]
+    )]
+    pub bench: bool,
+
+    /// Number of simulation steps to run in headless mode
+    #[arg(long, default_value_t = 600, requires = "headless")]
+    pub steps: u64,
+
+    /// Write the head position of every creature at every step to this CSV file (headless mode)
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    pub trace: Option<PathBuf>,
+
+    /// Start from a snapshot file instead of spawning new creatures
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["seed", "preset", "creature", "creatures"])]
+    pub load: Option<PathBuf>,
+
+    /// Write a snapshot of the simulation to this file once the run ends (headless mode)
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    pub save: Option<PathBuf>,
+
+    /// Record the seed, the inputs and a checksum of every step to this file
+    #[arg(long, value_name = "PATH", conflicts_with = "load")]
+    pub record: Option<PathBuf>,
+
+    /// Replay a recording and report the first step whose state differs from it
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["seed", "preset", "creature", "behavior", "creatures", "load", "record", "steps"]
+    )]
+    pub replay: Option<PathBuf>,
+
+    /// Snapshot file written with Ctrl+S and read with Ctrl+O
+    #[arg(long, value_name = "PATH", default_value = "snapshot.ron")]
+    pub snapshot: PathBuf,
+
+    /// Creature file written by the editor with Ctrl+E, the builder code is written next to it (.rs)
+    #[arg(long, value_name = "PATH", default_value = "creature.ron")]
+    pub export: PathBuf,
+
+    /// Render layers from bottom to top, separated by commas, the missing ones are not drawn
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "background,scenery,creatures,debug,hud"
+    )]
+    pub layers: Vec<Layer>,
+
+    /// Colors of the canvas and theme of the widgets
+    #[arg(long, value_enum, default_value_t = ThemeKind::Dark)]
+    pub theme: ThemeKind,
+
+    /// Palette file (RON) with custom canvas colors, used instead of the theme
+    #[arg(long, value_name = "PATH", conflicts_with = "theme")]
+    pub palette: Option<PathBuf>,
+
+    /// Loaded from the palette file
+    #[arg(skip)]
+    pub palette_def: Option<Palette>,
+}
+
+impl Default for Flags {
+    fn default() -> Self {
+        Self::parse_from(["procedural-animation"])
+    }
+}
+
+impl Flags {
+    // Parse the command line and exit with a usage error if the values don't make sense together
+    pub fn parse_and_validate() -> Self {
+        let mut flags = Self::parse();
+        if let Some(path) = &flags.trace {
+            Self::check_output(path);
+        }
+        if let Some(path) = &flags.save {
+            Self::check_output(path);
+        }
+        if let Some(path) = &flags.record {
+            Self::check_output(path);
+        }
+        if let Some(path) = &flags.load {
+            Self::check_input(path, "snapshot");
+        }
+        if let Some(path) = &flags.replay {
+            Self::check_input(path, "recording");
+        }
+        if let Some(path) = &flags.creature {
+            match CreatureDef::load(path) {
+                Ok(def) => flags.creature_def = Some(def),
+                Err(error) => Self::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("invalid creature file: {}", error),
+                    )
+                    .exit(),
+            }
+        }
+        if let Some(path) = &flags.behavior {
+            match BehaviorDef::load(path) {
+                Ok(def) => flags.behavior_def = Some(def),
+                Err(error) => Self::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("invalid behavior file: {}", error),
+                    )
+                    .exit(),
+            }
+        }
+        if let Some(path) = &flags.palette {
+            match Palette::load(path) {
+                Ok(palette) => flags.palette_def = Some(palette),
+                Err(error) => Self::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("invalid palette file: {}", error),
+                    )
+                    .exit(),
+            }
+        }
+        if flags.watch && flags.creature.is_none() && flags.behavior.is_none() {
+            Self::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "--watch needs a --creature or --behavior file to watch",
+                )
+                .exit();
+        }
+        flags.seed = Some(flags.seed.unwrap_or_else(rand::random));
+        flags
+    }
+
+    // The seed is always set after parse_and_validate
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_default()
+    }
+
+    // The palette file if one was given, otherwise the preset of the theme
+    pub fn palette(&self) -> Palette {
+        self.palette_def
+            .clone()
+            .unwrap_or_else(|| Palette::preset(self.theme))
+    }
+
+    fn check_input(path: &Path, kind: &str) {
+        if !path.is_file() {
+            Self::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("{} file '{}' does not exist", kind, path.display()),
+                )
+                .exit();
+        }
+    }
+
+    // Output files can be created, but their directory has to exist
+    fn check_output(path: &Path) {
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if !directory.is_dir() {
+            Self::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!(
+                        "cannot write '{}': directory '{}' does not exist",
+                        path.display(),
+                        directory.display()
+                    ),
+                )
+                .exit();
+        }
+        if path.is_dir() {
+            Self::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("cannot write '{}': it is a directory", path.display()),
+                )
+                .exit();
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::chain::*;
+use iced::{
+    widget::canvas::{Frame, LineCap, LineJoin, Path, Stroke},
+    Color, Point, Vector,
+};
+use serde::{Deserialize, Serialize};
+
+// How an inset contour is painted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ContourKind {
+    // Line along the inner edge, width in pixels
+    Line { width: f32 },
+    // Filled band between the inner and the outer edge, e.g. a dorsal stripe
+    Band,
+    // Band cut into one plate between every two circles, gap pixels apart, e.g. belly scales
+    Plates { gap: f32 },
+}
+
+// Contour following the body inside the outline, between two positions across it
+// Positions go from -1 (left edge) to 1 (right edge) as fractions of every circle's radius
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contour {
+    // First and last circle covered, the last one is clamped to the tail
+    pub from: usize,
+    pub to: usize,
+    pub inner: f32,
+    pub outer: f32,
+    pub kind: ContourKind,
+    #[serde(with = "crate::persist::color")]
+    pub color: Color,
+    // Points of both edges on the drawn chain, refilled every frame by update
+    #[serde(skip)]
+    inner_points: Vec<Vector>,
+    #[serde(skip)]
+    outer_points: Vec<Vector>,
+}
+
+impl Contour {
+    pub fn new(
+        from: usize,
+        to: usize,
+        inner: f32,
+        outer: f32,
+        kind: ContourKind,
+        color: Color,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            inner,
+            outer,
+            kind,
+            color,
+            inner_points: Vec::new(),
+            outer_points: Vec::new(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.from >= self.to {
+            return Err(format!(
+                "contour goes from circle {} to circle {}, from must be before to",
+                self.from, self.to
+            ));
+        }
+        if !(-1.0..=1.0).contains(&self.inner) || !(-1.0..=1.0).contains(&self.outer) {
+            return Err(String::from(
+                "contour inner and outer must be between -1 and 1",
+            ));
+        }
+        match self.kind {
+            ContourKind::Line { width } | ContourKind::Plates { gap: width }
+                if !width.is_finite() || width <= 0.0 =>
+            {
+                Err(String::from(
+                    "contour line width and plate gap must be positive",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Circles covered on a chain, None if the chain is too short
+    fn circles(&self, chain: &Chain) -> Option<(usize, usize)> {
+        let last = chain.circles.len().checked_sub(1)?;
+        let to = self.to.min(last);
+        (self.from < to).then_some((self.from, to))
+    }
+
+    // Follow the drawn chain, call whenever it changes
+    pub fn update(&mut self, chain: &Chain) {
+        let Some((from, to)) = self.circles(chain) else {
+            self.inner_points.clear();
+            self.outer_points.clear();
+            return;
+        };
+        chain.inset_points_into(from, to, self.inner, &mut self.inner_points);
+        match self.kind {
+            ContourKind::Line { .. } => self.outer_points.clear(),
+            _ => chain.inset_points_into(from, to, self.outer, &mut self.outer_points),
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let (inner, outer) = (&self.inner_points, &self.outer_points);
+        if inner.len() < 2 {
+            return;
+        }
+        match self.kind {
+            ContourKind::Line { width } => frame.stroke(
+                &Self::polyline(inner),
+                Stroke {
+                    style: self.color.into(),
+                    width,
+                    line_cap: LineCap::Round,
+                    line_join: LineJoin::Round,
+                    ..Default::default()
+                },
+            ),
+            ContourKind::Band => {
+                // Down one edge and back up the other one
+                let path = Path::new(|builder| {
+                    builder.move_to(Point::ORIGIN + inner[0]);
+                    for point in inner.iter().skip(1).chain(outer.iter().rev()) {
+                        builder.line_to(Point::ORIGIN + *point);
+                    }
+                    builder.close();
+                });
+                frame.fill(&path, self.color);
+            }
+            ContourKind::Plates { gap } => {
+                // The plates are pulled back from the circles between them, so the body shows through the gaps
+                let last = inner.len() - 1;
+                let path = Path::new(|builder| {
+                    for k in 0..last {
+                        let start = if k > 0 { gap / 2.0 } else { 0.0 };
+                        let end = if k + 1 < last { gap / 2.0 } else { 0.0 };
+                        let corners = [
+                            Self::shrink(inner[k], inner[k + 1], start, end),
+                            Self::shrink(outer[k], outer[k + 1], start, end),
+                        ];
+                        let [(a, b), (c, d)] = corners;
+                        builder.move_to(Point::ORIGIN + a);
+                        builder.line_to(Point::ORIGIN + b);
+                        builder.line_to(Point::ORIGIN + d);
+                        builder.line_to(Point::ORIGIN + c);
+                        builder.close();
+                    }
+                });
+                frame.fill(&path, self.color);
+            }
+        }
+    }
+
+    // Segment from a to b with start pixels cut from its beginning and end pixels from its end
+    fn shrink(a: Vector, b: Vector, start: f32, end: f32) -> (Vector, Vector) {
+        let length = Chain::vector_length(b - a);
+        if length <= start + end {
+            let middle = a + (b - a) * 0.5;
+            return (middle, middle);
+        }
+        let along = (b - a) * (1.0 / length);
+        (a + along * start, b - along * end)
+    }
+
+    fn polyline(points: &[Vector]) -> Path {
+        Path::new(|builder| {
+            if let Some(first) = points.first() {
+                builder.move_to(Point::ORIGIN + *first);
+            }
+            for point in points.iter().skip(1) {
+                builder.line_to(Point::ORIGIN + *point);
+            }
+        })
+    }
+}
@@ -0,0 +1,860 @@
+use crate::appendage::*;
+use crate::chain::*;
+use crate::contour::*;
+use crate::persist::*;
+use crate::rng::*;
+use crate::skin::*;
+use crate::snake::*;
+use crate::stroke::*;
+use crate::trail::*;
+use crate::world::*;
+use iced::Color;
+use iced::Vector;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+// Declarative description of a creature, loaded from a RON file
+// Angles are written in degrees, they are converted to radians when building the chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreatureDef {
+    #[serde(default)]
+    pub name: String,
+    // Radius of every circle, from head to tail
+    pub radii: Vec<f32>,
+    // Offset of every circle from the frontier of the previous one, all 0 if missing
+    #[serde(default)]
+    pub offsets: Vec<f32>,
+    // Outline angles for some of the circles, the others keep the default outline
+    #[serde(default)]
+    pub outline: BTreeMap<usize, Vec<f32>>,
+    #[serde(default = "CreatureDef::default_color")]
+    pub color: (u8, u8, u8),
+    #[serde(default = "CreatureDef::default_eye_color")]
+    pub eye_color: (u8, u8, u8),
+    #[serde(default = "CreatureDef::default_eyes")]
+    pub eyes: Vec<EyeDef>,
+    // Fins, spikes and antennae, e.g. (circle: 1, angle: 80.0, kind: Fin(length: 30.0, width: 16.0, rake: 45.0, flap: 20.0))
+    #[serde(default)]
+    pub appendages: Vec<AppendageDef>,
+    // Color of the appendages, the body color if missing
+    #[serde(default)]
+    pub appendage_color: Option<(u8, u8, u8)>,
+    // Track drawn behind the creature, e.g. (circle: 0, length: 120, style: Dots(every: 4, radius: 3.0))
+    #[serde(default)]
+    pub trail: Option<TrailDef>,
+    #[serde(default = "CreatureDef::default_pupil_color")]
+    pub pupil_color: (u8, u8, u8),
+    // What the pupils follow, Destination or Cursor
+    #[serde(default)]
+    pub gaze: Gaze,
+    // Blink every few seconds
+    #[serde(default = "CreatureDef::default_blink")]
+    pub blink: bool,
+    // Colors from head to tail, one per circle or spread evenly, color is used if empty
+    #[serde(default)]
+    pub colors: Vec<(u8, u8, u8)>,
+    // Color of the sides of the body, for a two-tone look
+    #[serde(default)]
+    pub belly_color: Option<(u8, u8, u8)>,
+    // Pattern painted over the body, e.g. Stripes(spacing: 40.0, width: 14.0)
+    #[serde(default)]
+    pub pattern: Pattern,
+    #[serde(default = "CreatureDef::default_pattern_color")]
+    pub pattern_color: (u8, u8, u8),
+    // Stripes and plates inside the outline, e.g. (inner: -0.15, outer: 0.15, kind: Band, color: (30, 30, 30))
+    #[serde(default)]
+    pub contours: Vec<ContourDef>,
+    // Stroke around the body, e.g. (body: (width: Taper(head: 6.0, tail: 1.0)))
+    #[serde(default)]
+    pub outline_stroke: OutlineStrokeDef,
+    #[serde(default)]
+    pub behavior: BehaviorDef,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EyeDef {
+    pub circle: usize,
+    // Angle from the direction of the circle, in degrees
+    pub angle: f32,
+    // Distance from the center of the circle, as a fraction of its radius
+    #[serde(default = "EyeDef::default_distance")]
+    pub distance: f32,
+    #[serde(default = "EyeDef::default_radius")]
+    pub radius: f32,
+    // Radius of the pupil, smaller than the eye, no pupil if 0 and half the eye if missing
+    #[serde(
+        with = "crate::persist::bare_option",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pupil: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppendageDef {
+    pub circle: usize,
+    // Angle from the direction of the circle, in degrees
+    pub angle: f32,
+    // Lengths in pixels, rake and flap in degrees
+    pub kind: AppendageKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrailDef {
+    // Circle whose positions are recorded, the head if missing
+    #[serde(default)]
+    pub circle: usize,
+    // Number of simulation steps kept
+    #[serde(default = "TrailDef::default_length")]
+    pub length: usize,
+    // Ribbon(width) or Dots(every, radius), sizes in pixels
+    #[serde(default = "TrailDef::default_style")]
+    pub style: TrailStyle,
+    // The body color if missing
+    #[serde(default)]
+    pub color: Option<(u8, u8, u8)>,
+}
+
+impl TrailDef {
+    fn default_length() -> usize {
+        Trail::default().length
+    }
+
+    fn default_style() -> TrailStyle {
+        Trail::default().style
+    }
+
+    pub fn trail(&self) -> Trail {
+        Trail::new(
+            self.circle,
+            self.length,
+            self.style.clone(),
+            self.color.map(|(r, g, b)| Color::from_rgb8(r, g, b)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContourDef {
+    // First and last circle covered, the whole body if missing
+    #[serde(default)]
+    pub from: usize,
+    #[serde(default = "ContourDef::default_to")]
+    pub to: usize,
+    // Positions across the body from -1 (left edge) to 1 (right edge), as fractions of the radii
+    pub inner: f32,
+    pub outer: f32,
+    // Line(width), Band or Plates(gap), sizes in pixels
+    pub kind: ContourKind,
+    pub color: (u8, u8, u8),
+}
+
+impl ContourDef {
+    fn default_to() -> usize {
+        usize::MAX
+    }
+
+    pub fn contour(&self) -> Contour {
+        let (r, g, b) = self.color;
+        Contour::new(
+            self.from,
+            self.to,
+            self.inner,
+            self.outer,
+            self.kind.clone(),
+            Color::from_rgb8(r, g, b),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutlineStrokeDef {
+    // Number of circles from the head whose outline uses the head style
+    pub head_circles: usize,
+    pub head: StrokeDef,
+    pub body: StrokeDef,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrokeDef {
+    // Constant(width), Taper(head, tail) or Curvature(min, max), in pixels
+    pub width: StrokeWidth,
+    // Colors from head to tail, the outline color of the palette if empty
+    #[serde(default)]
+    pub colors: Vec<(u8, u8, u8)>,
+}
+
+impl Default for OutlineStrokeDef {
+    fn default() -> Self {
+        Self::from_stroke(&OutlineStroke::default())
+    }
+}
+
+impl OutlineStrokeDef {
+    pub fn from_stroke(stroke: &OutlineStroke) -> Self {
+        let style = |style: &StrokeStyle| StrokeDef {
+            width: style.width.clone(),
+            colors: style
+                .colors
+                .iter()
+                .map(|color| {
+                    let [r, g, b, _] = color.into_rgba8();
+                    (r, g, b)
+                })
+                .collect(),
+        };
+        Self {
+            head_circles: stroke.head_circles,
+            head: style(&stroke.head),
+            body: style(&stroke.body),
+        }
+    }
+
+    pub fn stroke(&self) -> OutlineStroke {
+        let style = |def: &StrokeDef| StrokeStyle {
+            width: def.width.clone(),
+            colors: def
+                .colors
+                .iter()
+                .map(|(r, g, b)| Color::from_rgb8(*r, *g, *b))
+                .collect(),
+        };
+        OutlineStroke {
+            head_circles: self.head_circles,
+            head: style(&self.head),
+            body: style(&self.body),
+        }
+    }
+}
+
+// Movement parameters, speeds in pixels per second and angles in degrees
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BehaviorDef {
+    pub min_speed: f32,
+    pub max_speed: f32,
+    // Degrees per second
+    pub turn_rate: f32,
+    pub vision_angle: f32,
+}
+
+impl Default for BehaviorDef {
+    fn default() -> Self {
+        Self {
+            min_speed: 90.0,
+            max_speed: 360.0,
+            turn_rate: 54.0,
+            vision_angle: 30.0,
+        }
+    }
+}
+
+impl EyeDef {
+    fn default_distance() -> f32 {
+        0.9
+    }
+
+    fn default_radius() -> f32 {
+        6.0
+    }
+
+    // The given pupil, or one scaled to the eye
+    pub fn pupil_radius(&self) -> f32 {
+        self.pupil.unwrap_or(self.radius * Eye::PUPIL_RATIO)
+    }
+}
+
+impl CreatureDef {
+    fn default_color() -> (u8, u8, u8) {
+        (168, 58, 50)
+    }
+
+    fn default_eye_color() -> (u8, u8, u8) {
+        (255, 255, 255)
+    }
+
+    fn default_pupil_color() -> (u8, u8, u8) {
+        (20, 20, 24)
+    }
+
+    fn default_blink() -> bool {
+        true
+    }
+
+    fn default_pattern_color() -> (u8, u8, u8) {
+        (92, 26, 22)
+    }
+
+    fn default_eyes() -> Vec<EyeDef> {
+        vec![
+            EyeDef {
+                circle: 1,
+                angle: -36.0,
+                distance: EyeDef::default_distance(),
+                radius: EyeDef::default_radius(),
+                pupil: None,
+            },
+            EyeDef {
+                circle: 1,
+                angle: 36.0,
+                distance: EyeDef::default_distance(),
+                radius: EyeDef::default_radius(),
+                pupil: None,
+            },
+        ]
+    }
+
+    // Describe an existing snake, e.g. to export a body edited on screen
+    pub fn from_snake(snake: &Snake) -> Self {
+        let rgb = |color: Color| {
+            let [r, g, b, _] = color.into_rgba8();
+            (r, g, b)
+        };
+        let (radii, offsets) = snake
+            .chain
+            .circles
+            .iter()
+            .map(|circle| (circle.radius, circle.offset))
+            .unzip();
+        Self {
+            name: String::new(),
+            radii,
+            offsets,
+            outline: Self::outline_degrees(&snake.chain),
+            color: rgb(snake.color),
+            eye_color: rgb(snake.eye_color),
+            appendages: snake
+                .chain
+                .appendages
+                .iter()
+                .map(|appendage| AppendageDef {
+                    circle: appendage.circle,
+                    angle: appendage.angle.to_degrees(),
+                    kind: appendage.kind.to_degrees(),
+                })
+                .collect(),
+            appendage_color: snake.appendage_color.map(rgb),
+            trail: snake.trail.as_ref().map(|trail| TrailDef {
+                circle: trail.circle,
+                length: trail.length,
+                style: trail.style.clone(),
+                color: trail.color.map(rgb),
+            }),
+            pupil_color: rgb(snake.pupil_color),
+            gaze: snake.gaze,
+            blink: snake.blinking,
+            colors: snake.colors.iter().map(|c| rgb(*c)).collect(),
+            belly_color: snake.belly_color.map(rgb),
+            pattern: snake.pattern.clone(),
+            pattern_color: rgb(snake.pattern_color),
+            contours: snake
+                .contours
+                .iter()
+                .map(|contour| ContourDef {
+                    from: contour.from,
+                    to: contour.to,
+                    inner: contour.inner,
+                    outer: contour.outer,
+                    kind: contour.kind.clone(),
+                    color: rgb(contour.color),
+                })
+                .collect(),
+            outline_stroke: OutlineStrokeDef::from_stroke(&snake.outline_stroke),
+            eyes: snake
+                .eyes
+                .iter()
+                .map(|eye| EyeDef {
+                    circle: eye.circle,
+                    angle: eye.angle.to_degrees(),
+                    distance: eye.distance,
+                    radius: eye.radius,
+                    pupil: eye.pupil,
+                })
+                .collect(),
+            behavior: BehaviorDef {
+                min_speed: snake.min_speed,
+                max_speed: snake.max_speed,
+                turn_rate: snake.turn_rate.to_degrees(),
+                vision_angle: snake.vision_angle.to_degrees(),
+            },
+        }
+    }
+
+    // Outline angles of a chain in degrees, circles with the default outline are left out
+    pub fn outline_degrees(chain: &Chain) -> BTreeMap<usize, Vec<f32>> {
+        let n = chain.circles.len();
+        (0..n)
+            .filter_map(|i| {
+                let angles: Vec<f32> = chain.outlines[i]
+                    .iter()
+                    .chain(chain.outlines[n + i].iter())
+                    .map(|a| a.to_degrees())
+                    .collect();
+                let default = angles.len() == 2
+                    && (angles[0] - 90.0).abs() < 1e-3
+                    && (angles[1] - 270.0).abs() < 1e-3;
+                (!default).then_some((i, angles))
+            })
+            .collect()
+    }
+
+    // Remove circle i from the body, what is attached to it goes away and what comes after it moves up one index
+    pub fn remove_circle(&mut self, i: usize) {
+        let shift = |circle: usize| match circle > i {
+            true => circle - 1,
+            false => circle,
+        };
+        self.eyes.retain(|eye| eye.circle != i);
+        for eye in self.eyes.iter_mut() {
+            eye.circle = shift(eye.circle);
+        }
+        self.appendages.retain(|appendage| appendage.circle != i);
+        for appendage in self.appendages.iter_mut() {
+            appendage.circle = shift(appendage.circle);
+        }
+        if self.trail.as_ref().is_some_and(|trail| trail.circle == i) {
+            self.trail = None;
+        }
+        if let Some(trail) = self.trail.as_mut() {
+            trail.circle = shift(trail.circle);
+        }
+        // A contour ending on the removed circle now ends on the one before it, the whole body stays the whole body
+        for contour in self.contours.iter_mut() {
+            contour.from = shift(contour.from);
+            if contour.to >= i && contour.to != ContourDef::default_to() {
+                contour.to = contour.to.saturating_sub(1);
+            }
+        }
+        self.contours.retain(|contour| contour.from < contour.to);
+        if i < self.outline_stroke.head_circles {
+            self.outline_stroke.head_circles -= 1;
+        }
+        self.outline.remove(&i);
+        self.outline = std::mem::take(&mut self.outline)
+            .into_iter()
+            .map(|(circle, angles)| (shift(circle), angles))
+            .collect();
+        // Colors given one per circle stay on their circles
+        if self.colors.len() == self.radii.len() && i < self.colors.len() {
+            self.colors.remove(i);
+        }
+        if i < self.offsets.len() {
+            self.offsets.remove(i);
+        }
+        if i < self.radii.len() {
+            self.radii.remove(i);
+        }
+    }
+
+    // Read, parse and validate a creature file
+    pub fn load(path: &Path) -> Result<Self, RonError> {
+        let def: Self = load_ron(path)?;
+        def.validate()
+            .map_err(|message| RonError::Invalid(path.to_path_buf(), message))?;
+        Ok(def)
+    }
+
+    // Check the values that parse fine but can't be turned into a creature
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.radii.len();
+        if n < 2 {
+            return Err(format!(
+                "radii has {} value(s), a creature needs at least 2 circles",
+                n
+            ));
+        }
+        if let Some((i, r)) = self
+            .radii
+            .iter()
+            .enumerate()
+            .find(|(_, r)| !Self::positive(**r))
+        {
+            return Err(format!("radii[{}] is {}, radii must be positive", i, r));
+        }
+        if !self.offsets.is_empty() && self.offsets.len() != n {
+            return Err(format!(
+                "offsets has {} values but radii has {}, give one offset per circle or leave offsets out",
+                self.offsets.len(),
+                n
+            ));
+        }
+        if let Some(i) = self.offsets.iter().position(|o| !o.is_finite()) {
+            return Err(format!("offsets[{}] is not a finite number", i));
+        }
+        for (circle, angles) in self.outline.iter() {
+            if *circle >= n {
+                return Err(format!(
+                    "outline has angles for circle {} but the creature only has {} circles (0 to {})",
+                    circle,
+                    n,
+                    n - 1
+                ));
+            }
+            if angles.iter().any(|a| !a.is_finite()) {
+                return Err(format!(
+                    "outline angles of circle {} must be finite numbers",
+                    circle
+                ));
+            }
+        }
+        for (i, eye) in self.eyes.iter().enumerate() {
+            if eye.circle >= n {
+                return Err(format!(
+                    "eyes[{}] is attached to circle {} but the creature only has {} circles (0 to {})",
+                    i,
+                    eye.circle,
+                    n,
+                    n - 1
+                ));
+            }
+            if !Self::positive(eye.radius) {
+                return Err(format!("eyes[{}] radius must be positive", i));
+            }
+            let pupil = eye.pupil_radius();
+            if !pupil.is_finite() || pupil < 0.0 || pupil >= eye.radius {
+                return Err(format!(
+                    "eyes[{}] pupil is {}, it must be at least 0 and smaller than the radius {}",
+                    i, pupil, eye.radius
+                ));
+            }
+        }
+        for (i, appendage) in self.appendages.iter().enumerate() {
+            if appendage.circle >= n {
+                return Err(format!(
+                    "appendages[{}] is attached to circle {} but the creature only has {} circles (0 to {})",
+                    i,
+                    appendage.circle,
+                    n,
+                    n - 1
+                ));
+            }
+            if !appendage.angle.is_finite() {
+                return Err(format!("appendages[{}] angle must be a finite number", i));
+            }
+            appendage
+                .kind
+                .validate()
+                .map_err(|message| format!("appendages[{}]: {}", i, message))?;
+        }
+        if let Some(trail) = &self.trail {
+            if trail.circle >= n {
+                return Err(format!(
+                    "trail follows circle {} but the creature only has {} circles (0 to {})",
+                    trail.circle,
+                    n,
+                    n - 1
+                ));
+            }
+            trail
+                .trail()
+                .validate()
+                .map_err(|message| format!("trail: {}", message))?;
+        }
+        for (i, contour) in self.contours.iter().enumerate() {
+            if contour.from >= n - 1 {
+                return Err(format!(
+                    "contours[{}] starts at circle {} but the creature only has {} circles, it needs two of them",
+                    i, contour.from, n
+                ));
+            }
+            contour
+                .contour()
+                .validate()
+                .map_err(|message| format!("contours[{}]: {}", i, message))?;
+        }
+        if self.outline_stroke.head_circles > n {
+            return Err(format!(
+                "outline_stroke has {} head circles but the creature only has {} circles",
+                self.outline_stroke.head_circles, n
+            ));
+        }
+        self.outline_stroke
+            .stroke()
+            .validate()
+            .map_err(|message| format!("outline_stroke.{}", message))?;
+        self.pattern
+            .validate()
+            .map_err(|message| format!("pattern: {}", message))?;
+        self.behavior
+            .validate()
+            .map_err(|message| format!("behavior.{}", message))
+    }
+
+    fn positive(value: f32) -> bool {
+        value.is_finite() && value > 0.0
+    }
+
+    // Build the chain of the creature, stretched out to the right before being bound together
+    pub fn build_chain(&self, rng: &mut SimRng) -> Chain {
+        let heights: Vec<f32> = (0..self.radii.len())
+            .map(|_| rng.gen_range(-300.0..300.0))
+            .collect();
+        self.chain_with_positions(|i: usize, r: f32| (Some(i as f32 * r * 10.0), Some(heights[i])))
+    }
+
+    // Build the chain of the creature laid out in a straight line behind a head
+    pub fn build_chain_behind(&self, head: Vector, direction: Vector) -> Chain {
+        let mut chain = self.chain_with_positions(|i: usize, r: f32| {
+            let position = head - direction * (i as f32 * r * 2.0);
+            (Some(position.x), Some(position.y))
+        });
+        chain.circles[0].direction = direction;
+        chain
+    }
+
+    fn chain_with_positions(
+        &self,
+        get_position: impl Fn(usize, f32) -> (Option<f32>, Option<f32>),
+    ) -> Chain {
+        let n = self.radii.len();
+        let outline = (0..n)
+            .map(|i| {
+                self.outline
+                    .get(&i)
+                    .map(|angles| angles.iter().map(|a| a.to_radians()).collect())
+                    .unwrap_or_default()
+            })
+            .collect();
+        Chain::new()
+            .circles_radii(self.radii.clone())
+            .circles_offsets(self.offsets.clone())
+            .circles_positions(get_position)
+            .default_outline()
+            .refine_outline(outline)
+            .appendages(self.appendage_list())
+            .build()
+    }
+
+    // Appendages of the chain, with the angles in radians
+    pub fn appendage_list(&self) -> Vec<Appendage> {
+        self.appendages
+            .iter()
+            .map(|def| Appendage::new(def.circle, def.angle.to_radians(), def.kind.to_radians()))
+            .collect()
+    }
+
+    // Spawn a new creature with its head at a random point of the world
+    pub fn spawn(&self, world: &World, mut rng: SimRng) -> Snake {
+        let chain = self.build_chain(&mut rng);
+        let mut snake = Snake::with_chain(chain, world, rng);
+        self.apply(&mut snake);
+        snake
+    }
+
+    // Replace the body of a snake, keeping its head where it is and facing the same way
+    pub fn rebuild(&self, snake: &mut Snake) {
+        let head = &snake.chain.circles[0];
+        let mut direction = head.direction;
+        if Chain::vector_length(direction) <= f32::EPSILON {
+            direction = Vector::new(-1.0, 0.0);
+        }
+        let direction = direction * (1.0 / Chain::vector_length(direction));
+        snake.replace_chain(self.build_chain_behind(head.position, direction));
+        self.apply(snake);
+    }
+
+    // Copy the looks and behavior onto an existing snake, its body is left untouched
+    pub fn apply(&self, snake: &mut Snake) {
+        let (r, g, b) = self.color;
+        snake.color = Color::from_rgb8(r, g, b);
+        let (r, g, b) = self.eye_color;
+        snake.eye_color = Color::from_rgb8(r, g, b);
+        let (r, g, b) = self.pupil_color;
+        snake.pupil_color = Color::from_rgb8(r, g, b);
+        snake.gaze = self.gaze;
+        snake.trail = self.trail.as_ref().map(TrailDef::trail);
+        snake.appendage_color = self
+            .appendage_color
+            .map(|(r, g, b)| Color::from_rgb8(r, g, b));
+        snake.blinking = self.blink;
+        snake.colors = self
+            .colors
+            .iter()
+            .map(|(r, g, b)| Color::from_rgb8(*r, *g, *b))
+            .collect();
+        snake.belly_color = self.belly_color.map(|(r, g, b)| Color::from_rgb8(r, g, b));
+        snake.pattern = self.pattern.clone();
+        let (r, g, b) = self.pattern_color;
+        snake.pattern_color = Color::from_rgb8(r, g, b);
+        snake.contours = self.contours.iter().map(ContourDef::contour).collect();
+        snake.outline_stroke = self.outline_stroke.stroke();
+        snake.eyes = self
+            .eyes
+            .iter()
+            .map(|eye| Eye {
+                circle: eye.circle,
+                angle: eye.angle.to_radians(),
+                distance: eye.distance,
+                radius: eye.radius,
+                pupil: eye.pupil,
+            })
+            .collect();
+        self.behavior.apply(snake);
+    }
+}
+
+impl BehaviorDef {
+    // Read, parse and validate a behavior file
+    pub fn load(path: &Path) -> Result<Self, RonError> {
+        let def: Self = load_ron(path)?;
+        def.validate()
+            .map_err(|message| RonError::Invalid(path.to_path_buf(), message))?;
+        Ok(def)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !CreatureDef::positive(self.min_speed) {
+            return Err(String::from("min_speed must be positive"));
+        }
+        if !CreatureDef::positive(self.max_speed) || self.max_speed < self.min_speed {
+            return Err(format!(
+                "max_speed ({}) can't be lower than min_speed ({})",
+                self.max_speed, self.min_speed
+            ));
+        }
+        if !CreatureDef::positive(self.turn_rate) {
+            return Err(String::from("turn_rate must be positive"));
+        }
+        if !CreatureDef::positive(self.vision_angle) || self.vision_angle > 180.0 {
+            return Err(String::from(
+                "vision_angle must be between 0 and 180 degrees",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, snake: &mut Snake) {
+        snake.min_speed = self.min_speed;
+        snake.max_speed = self.max_speed;
+        snake.turn_rate = self.turn_rate.to_radians();
+        snake.vision_angle = self.vision_angle.to_radians();
+        snake.modify_speed(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> CreatureDef {
+        ron::from_str(source).unwrap()
+    }
+
+    fn error(source: &str) -> String {
+        parse(source).validate().unwrap_err()
+    }
+
+    #[test]
+    fn shipped_creatures_are_valid() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("creatures");
+        for name in ["chunky.ron", "dragon.ron"] {
+            if let Err(error) = CreatureDef::load(&dir.join(name)) {
+                panic!("{}", error);
+            }
+        }
+    }
+
+    #[test]
+    fn radii_alone_make_a_creature() {
+        assert_eq!(parse("(radii: [20.0, 15.0])").validate(), Ok(()));
+    }
+
+    #[test]
+    fn bad_bodies_are_rejected() {
+        assert!(error("(radii: [20.0])").contains("at least 2 circles"));
+        assert!(error("(radii: [20.0, -1.0])").contains("radii[1]"));
+        assert!(error("(radii: [20.0, 15.0], offsets: [0.0])").contains("offsets has 1"));
+    }
+
+    #[test]
+    fn attachments_must_be_on_existing_circles() {
+        assert!(
+            error("(radii: [20.0, 15.0], eyes: [(circle: 2, angle: 30.0)])").contains("eyes[0]")
+        );
+        assert!(error("(radii: [20.0, 15.0], trail: Some((circle: 5)))").contains("trail"));
+    }
+
+    #[test]
+    fn missing_pupils_fit_small_eyes() {
+        let def = parse("(radii: [20.0, 15.0], eyes: [(circle: 0, angle: 30.0, radius: 2.5)])");
+        assert_eq!(def.validate(), Ok(()));
+        assert_eq!(def.eyes[0].pupil_radius(), 1.25);
+        assert!(error(
+            "(radii: [20.0, 15.0], eyes: [(circle: 0, angle: 30.0, radius: 2.5, pupil: 3.0)])"
+        )
+        .contains("pupil is 3"));
+    }
+
+    #[test]
+    fn snapshot_eyes_without_pupils_scale_them_to_the_eye() {
+        let eye: Eye =
+            ron::from_str("(circle: 1, angle: 0.5, distance: 0.9, radius: 2.5)").unwrap();
+        assert_eq!(eye.pupil, None);
+        assert_eq!(eye.pupil_radius(), 1.25);
+        assert!(!ron::to_string(&eye).unwrap().contains("pupil"));
+        let eye: Eye =
+            ron::from_str("(circle: 1, angle: 0.5, distance: 0.9, radius: 2.5, pupil: 1.0)")
+                .unwrap();
+        assert_eq!(eye.pupil_radius(), 1.0);
+    }
+
+    #[test]
+    fn pupils_are_written_as_plain_numbers() {
+        let def = parse("(radii: [20.0, 15.0], eyes: [(circle: 0, angle: 30.0, pupil: 2.0)])");
+        assert_eq!(def.eyes[0].pupil, Some(2.0));
+        let source = ron::to_string(&def).unwrap();
+        assert!(source.contains("pupil:2.0"));
+        assert_eq!(ron::from_str::<CreatureDef>(&source).unwrap(), def);
+        let default = parse("(radii: [20.0, 15.0])");
+        assert!(!ron::to_string(&default).unwrap().contains("pupil:"));
+    }
+
+    #[test]
+    fn bad_contours_are_rejected() {
+        let contour = |range: &str| {
+            error(&format!(
+                "(radii: [20.0, 15.0, 10.0], contours: [({}, inner: 0.0, outer: 0.5, kind: Band, color: (0, 0, 0))])",
+                range
+            ))
+        };
+        assert!(contour("from: 18446744073709551615").contains("contours[0] starts at circle"));
+        assert!(contour("from: 2").contains("contours[0] starts at circle"));
+        assert!(contour("from: 1, to: 1").contains("from must be before to"));
+        assert_eq!(
+            parse("(radii: [20.0, 15.0, 10.0], contours: [(from: 1, inner: 0.0, outer: 0.5, kind: Band, color: (0, 0, 0))])")
+                .validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn bad_behavior_is_rejected() {
+        assert!(
+            error("(radii: [20.0, 15.0], behavior: (min_speed: 100.0, max_speed: 50.0))")
+                .starts_with("behavior.max_speed")
+        );
+    }
+
+    #[test]
+    fn from_snake_round_trips() {
+        let def =
+            CreatureDef::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("creatures/dragon.ron"))
+                .unwrap();
+        let snake = def.spawn(&World::default(), SimRng::new(1));
+        let exported = CreatureDef::from_snake(&snake);
+        assert_eq!(exported.validate(), Ok(()));
+        assert_eq!(exported.radii, def.radii);
+        // Angles go through radians and back and pick up rounding errors
+        assert_eq!(exported.appendages.len(), def.appendages.len());
+        assert_eq!(exported.contours, def.contours);
+        assert_eq!(exported.outline_stroke, def.outline_stroke);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::chain::*;
+use crate::creature::*;
+use crate::palette::*;
+use crate::persist::*;
+use crate::snake::*;
+use iced::{
+    widget::canvas::{Frame, Path, Stroke},
+    Color, Point, Vector,
+};
+use std::{
+    f32::consts::PI,
+    fmt::Write,
+    fs,
+    path::{Path as FilePath, PathBuf},
+};
+
+// A change made to the chain in the editor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Select(usize),
+    // Move a circle to a point, the circles in front of it move along and the others follow
+    Move(usize, Vector),
+    // Add a circle behind the tail, pointing towards a point
+    Append(Vector),
+    // Remove the selected circle
+    Remove,
+    // Scale the radius of a circle by mouse wheel lines
+    Radius(usize, f32),
+    // Change the offset of a circle by mouse wheel lines
+    Offset(usize, f32),
+    // Add or remove the outline point of a circle closest to an angle
+    ToggleAngle(usize, f32),
+}
+
+// What is under the cursor in the editor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hit {
+    // Near the rim of a circle, with the angle from its direction
+    Rim(usize, f32),
+    Inside(usize),
+}
+
+// Edits the body of a creature, starting from a copy of an existing one
+pub struct Editor {
+    pub chain: Chain,
+    // Outline angles of every circle in radians, as given to refine_outline (empty means default)
+    angles: Vec<Vec<f32>>,
+    pub selected: Option<usize>,
+    // Looks and behavior kept when exporting a definition file
+    base: CreatureDef,
+    color: Color,
+}
+
+impl Editor {
+    // Distance on screen from a rim, or between two outline points, that still counts as a hit
+    pub const TOLERANCE: f32 = 8.0;
+    const MIN_RADIUS: f32 = 2.0;
+    const MAX_RADIUS: f32 = 200.0;
+    const MAX_OFFSET: f32 = 100.0;
+
+    pub fn new(snake: &Snake) -> Self {
+        let chain = snake.chain.clone();
+        let n = chain.circles.len();
+        let angles = (0..n)
+            .map(|i| {
+                chain.outlines[i]
+                    .iter()
+                    .chain(chain.outlines[n + i].iter())
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        let mut editor = Self {
+            chain,
+            angles,
+            selected: None,
+            base: CreatureDef::from_snake(snake),
+            color: snake.color,
+        };
+        editor.rebuild();
+        editor
+    }
+
+    // Build the chain again with the builder, so the preview is exactly what gets exported
+    fn rebuild(&mut self) {
+        let old = &self.chain.circles;
+        let mut chain = Chain::new()
+            .circles_radii(old.iter().map(|c| c.radius).collect())
+            .circles_offsets(old.iter().map(|c| c.offset).collect())
+            .circles_positions(|i: usize, _| (Some(old[i].position.x), Some(old[i].position.y)))
+            .default_outline()
+            .refine_outline(self.angles.clone())
+            .appendages(self.chain.appendages.clone())
+            .build();
+        for (circle, old) in chain.circles.iter_mut().zip(old.iter()) {
+            circle.direction = old.direction;
+        }
+        chain.update_positions(0);
+        self.chain = chain;
+    }
+
+    pub fn apply(&mut self, edit: &Edit) {
+        let n = self.chain.circles.len();
+        match *edit {
+            Edit::Select(i) => self.selected = Some(i).filter(|i| *i < n),
+            Edit::Move(i, position) if i < n => {
+                let delta = position - self.chain.circles[i].position;
+                for circle in self.chain.circles[..=i].iter_mut() {
+                    circle.position = circle.position + delta;
+                }
+            }
+            Edit::Append(position) => {
+                let mut circle = self.chain.circles[n - 1].clone();
+                circle.offset = 0.0;
+                circle.position = position;
+                self.chain.circles.push(circle);
+                self.angles.push(Vec::new());
+                self.selected = Some(n);
+            }
+            Edit::Remove => {
+                // A chain needs at least two circles
+                if let Some(i) = self.selected.filter(|i| *i < n && n > 2) {
+                    self.chain.circles.remove(i);
+                    self.angles.remove(i);
+                    self.chain
+                        .appendages
+                        .retain(|appendage| appendage.circle != i);
+                    for appendage in self.chain.appendages.iter_mut() {
+                        if appendage.circle > i {
+                            appendage.circle -= 1;
+                        }
+                    }
+                    self.base.remove_circle(i);
+                    self.selected = None;
+                }
+            }
+            Edit::Radius(i, lines) if i < n => {
+                let circle = &mut self.chain.circles[i];
+                circle.radius =
+                    (circle.radius * 1.1_f32.powf(lines)).clamp(Self::MIN_RADIUS, Self::MAX_RADIUS);
+            }
+            Edit::Offset(i, lines) if i < n => {
+                let circle = &mut self.chain.circles[i];
+                circle.offset =
+                    (circle.offset + lines * 2.0).clamp(-Self::MAX_OFFSET, Self::MAX_OFFSET);
+            }
+            Edit::ToggleAngle(i, angle) if i < n => self.toggle_angle(i, angle),
+            _ => {}
+        }
+        self.rebuild();
+    }
+
+    // Remove the outline point next to the angle, or add one if there is none
+    // Removing the last point of a circle brings back its default outline
+    fn toggle_angle(&mut self, i: usize, angle: f32) {
+        let mut angles = match self.angles[i].is_empty() {
+            true => vec![PI / 2.0, 3.0 * PI / 2.0],
+            false => self.angles[i].clone(),
+        };
+        let radius = self.chain.circles[i].radius;
+        let near = angles.iter().position(|a| {
+            let delta = (a - angle).rem_euclid(2.0 * PI);
+            delta.min(2.0 * PI - delta) * radius < Self::TOLERANCE
+        });
+        match near {
+            Some(index) => {
+                angles.remove(index);
+            }
+            None => angles.push(angle),
+        }
+        self.angles[i] = angles;
+    }
+
+    // Find the circle under a world point, tolerance is in world units
+    pub fn hit(&self, point: Vector, tolerance: f32) -> Option<Hit> {
+        let distances = self
+            .chain
+            .circles
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i, Chain::vector_length(point - c.position), c.radius));
+        let rim = distances
+            .clone()
+            .map(|(i, d, r)| (i, (d - r).abs()))
+            .filter(|(_, gap)| *gap < tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, _)) = rim {
+            let circle = &self.chain.circles[i];
+            let v = point - circle.position;
+            let d = circle.direction;
+            let angle = (d.x * v.y - d.y * v.x).atan2(d.x * v.x + d.y * v.y);
+            return Some(Hit::Rim(i, angle.rem_euclid(2.0 * PI)));
+        }
+        distances
+            .filter(|(_, d, r)| d < r)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _, _)| Hit::Inside(i))
+    }
+
+    // Definition of the edited creature, with the looks and behavior of the creature it started from
+    pub fn creature(&self) -> CreatureDef {
+        // The attachments of removed circles are already gone from base, see Edit::Remove
+        let mut def = self.base.clone();
+        def.radii = self.chain.circles.iter().map(|c| c.radius).collect();
+        def.offsets = self.chain.circles.iter().map(|c| c.offset).collect();
+        def.outline = CreatureDef::outline_degrees(&self.chain);
+        def
+    }
+
+    // Rust code building the same chain with ChainBuilder
+    pub fn builder_code(&self) -> String {
+        let list = |values: Vec<f32>| {
+            values
+                .iter()
+                .map(|v| format!("{:?}", v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let circles = &self.chain.circles;
+        let mut code = String::from("Chain::new()\n");
+        let _ = writeln!(
+            code,
+            "    .circles_radii(vec![{}])",
+            list(circles.iter().map(|c| c.radius).collect())
+        );
+        let _ = writeln!(
+            code,
+            "    .circles_offsets(vec![{}])",
+            list(circles.iter().map(|c| c.offset).collect())
+        );
+        code.push_str(
+            "    .circles_positions(|i: usize, r: f32| (Some(i as f32 * r * 2.0), Some(0.0)))\n",
+        );
+        code.push_str("    .default_outline()\n");
+        code.push_str("    .refine_outline(vec![\n");
+        for angles in self.angles.iter() {
+            let _ = writeln!(code, "        vec![{}],", list(angles.clone()));
+        }
+        code.push_str("    ])\n");
+        if !self.chain.appendages.is_empty() {
+            code.push_str("    .appendages(vec![\n");
+            for appendage in self.chain.appendages.iter() {
+                let _ = writeln!(
+                    code,
+                    "        Appendage::new({}, {:?}, AppendageKind::{:?}),",
+                    appendage.circle, appendage.angle, appendage.kind
+                );
+            }
+            code.push_str("    ])\n");
+        }
+        code.push_str("    .build()\n");
+        code
+    }
+
+    // Write the definition file and the builder code next to it, with an .rs extension
+    pub fn export(&self, path: &FilePath) -> Result<PathBuf, RonError> {
+        save_ron(&self.creature(), path)?;
+        let code_path = path.with_extension("rs");
+        fs::write(&code_path, self.builder_code())
+            .map_err(|e| RonError::Io(code_path.clone(), e))?;
+        Ok(code_path)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, palette: &Palette) {
+        frame.fill(
+            &self.chain.appendages_path(Point::ORIGIN),
+            Color {
+                a: 0.4,
+                ..self.color
+            },
+        );
+        let outline = self.chain.outline_path(Point::ORIGIN);
+        frame.fill(
+            &outline,
+            Color {
+                a: 0.6,
+                ..self.color
+            },
+        );
+        frame.stroke(
+            &outline,
+            Stroke {
+                style: palette.foreground.into(),
+                width: 2.0,
+                ..Default::default()
+            },
+        );
+        frame.stroke(
+            &self.chain.circle_path(Point::ORIGIN),
+            Stroke {
+                style: palette.overlay_line.into(),
+                width: 1.0,
+                ..Default::default()
+            },
+        );
+        if let Some(circle) = self.selected.and_then(|i| self.chain.circles.get(i)) {
+            frame.stroke(
+                &circle.path(Point::ORIGIN),
+                Stroke {
+                    style: palette.highlight.into(),
+                    width: 2.0,
+                    ..Default::default()
+                },
+            );
+        }
+        let points = Path::new(|builder| {
+            for point in self.chain.outline_points() {
+                builder.circle(Point::ORIGIN + point, 3.0);
+            }
+        });
+        frame.fill(&points, palette.highlight);
+        let head = &self.chain.circles[0];
+        frame.stroke(
+            &Path::line(
+                Point::ORIGIN + head.position,
+                Point::ORIGIN + head.point_on_circle(head.direction),
+            ),
+            Stroke {
+                style: palette.heading.into(),
+                width: 2.0,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::*;
+    use crate::world::*;
+
+    fn editor() -> Editor {
+        let def = CreatureDef::load(
+            &FilePath::new(env!("CARGO_MANIFEST_DIR")).join("creatures/dragon.ron"),
+        )
+        .unwrap();
+        Editor::new(&def.spawn(&World::default(), SimRng::new(1)))
+    }
+
+    #[test]
+    fn removing_a_circle_moves_the_attachments_behind_it() {
+        let mut editor = editor();
+        let before = editor.creature();
+        editor.apply(&Edit::Select(2));
+        editor.apply(&Edit::Remove);
+        let after = editor.creature();
+        assert_eq!(after.radii.len(), before.radii.len() - 1);
+        assert_eq!(after.validate(), Ok(()));
+
+        // The fins on circle 2 are gone, the spikes on circle 4 are now on circle 3
+        let circles = |def: &CreatureDef| {
+            def.appendages
+                .iter()
+                .map(|appendage| appendage.circle)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(circles(&before), [0, 0, 2, 2, 4, 4, 6, 6, 8, 8]);
+        assert_eq!(circles(&after), [0, 0, 3, 3, 5, 5, 7, 7]);
+        let chain: Vec<usize> = editor.chain.appendages.iter().map(|a| a.circle).collect();
+        assert_eq!(chain, circles(&after));
+        assert_eq!(after.contours[0].from, before.contours[0].from);
+        assert_eq!(after.contours[0].to, before.contours[0].to - 1);
+    }
+
+    #[test]
+    fn removing_the_head_moves_the_head_style_and_eyes() {
+        let mut editor = editor();
+        let before = editor.creature();
+        editor.apply(&Edit::Select(0));
+        editor.apply(&Edit::Remove);
+        let after = editor.creature();
+        assert_eq!(after.outline_stroke.head_circles, 0);
+        assert!(after.trail.is_none());
+        assert!(after.eyes.len() <= before.eyes.len());
+        assert!(after.eyes.iter().all(|eye| eye.circle < after.radii.len()));
+        assert_eq!(after.validate(), Ok(()));
+    }
+}
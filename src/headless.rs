@@ -0,0 +1,108 @@
+use crate::app::*;
+use crate::bench;
+use crate::cli::*;
+use crate::replay::*;
+use crate::sim::*;
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+// Run the simulation without a window, optionally writing a trace of the head positions
+pub fn run(flags: &Flags) -> Result<(), Box<dyn Error>> {
+    if flags.bench {
+        return bench::run(flags);
+    }
+    let mut replay = match &flags.replay {
+        Some(path) => Some(Replayer::load(path)?),
+        None => None,
+    };
+    let mut sim = match (&replay, &flags.load) {
+        (Some(replay), _) => replay.simulation(),
+        (None, Some(path)) => {
+            let sim = Simulation::load(path)?;
+            println!("Loaded snapshot '{}' at step {}", path.display(), sim.steps);
+            sim
+        }
+        (None, None) => Setup::new(flags).simulation(),
+    };
+    let mut recorder = flags
+        .record
+        .as_ref()
+        .map(|path| Recorder::new(Setup::new(flags), path, &sim));
+    // A replay runs as many steps as were recorded
+    let steps = match &replay {
+        Some(replay) => {
+            replay.check(&sim)?;
+            replay.steps()
+        }
+        None => flags.steps,
+    };
+    let mut trace = match &flags.trace {
+        Some(path) => {
+            let mut file = BufWriter::new(File::create(path)?);
+            writeln!(file, "step,creature,x,y,speed")?;
+            Some(file)
+        }
+        None => None,
+    };
+
+    for _ in 0..steps {
+        if let Some(replay) = replay.as_mut() {
+            replay.feed(&mut sim);
+        }
+        sim.update(SIM_DT);
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.step(&sim);
+        }
+        if let Some(replay) = &replay {
+            replay.check(&sim)?;
+        }
+        if let Some(file) = trace.as_mut() {
+            for (i, snake) in sim.snakes.iter().enumerate() {
+                let head = snake.chain.circles[0].position;
+                writeln!(
+                    file,
+                    "{},{},{:.3},{:.3},{:.3}",
+                    sim.steps, i, head.x, head.y, snake.speed
+                )?;
+            }
+        }
+    }
+    if let Some(mut file) = trace {
+        file.flush()?;
+    }
+    if let Some(path) = &flags.save {
+        sim.save(path)?;
+        println!("Saved snapshot '{}'", path.display());
+    }
+    if let Some(recorder) = &recorder {
+        recorder.save()?;
+        println!("Saved recording '{}'", recorder.path().display());
+    }
+
+    match (&flags.replay, &flags.load) {
+        (Some(_), _) => println!(
+            "Replayed {} steps ({:.1}s), every checksum matches the recording",
+            steps,
+            steps as f32 * SIM_DT
+        ),
+        (None, Some(_)) => println!(
+            "Simulated {} steps ({:.1}s)",
+            flags.steps,
+            flags.steps as f32 * SIM_DT
+        ),
+        (None, None) => println!(
+            "Simulated {} steps ({:.1}s) with seed {}",
+            sim.steps,
+            sim.steps as f32 * SIM_DT,
+            flags.seed()
+        ),
+    }
+    for (i, snake) in sim.snakes.iter().enumerate() {
+        let head = snake.chain.circles[0].position;
+        println!("Creature {}: head at ({:.1}, {:.1})", i, head.x, head.y);
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use crate::replay::*;
+use std::collections::VecDeque;
+
+// One undoable change: the inputs that revert it, applied in order
+struct Entry {
+    revert: Vec<Input>,
+    // The input that was done, used to merge the changes of a slider drag
+    done: Option<Input>,
+}
+
+// Bounded undo and redo stacks of the interactive changes
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    // The last undo entry belongs to a slider drag that is still going on
+    dragging: bool,
+}
+
+impl History {
+    // Oldest changes are forgotten past this many entries
+    const LIMIT: usize = 100;
+
+    // Remember a change that is about to be made, revert comes from Input::revert
+    pub fn push(&mut self, done: &Input, revert: Vec<Input>) {
+        self.dragging = false;
+        if revert.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.push_entry(Entry {
+            revert,
+            done: Some(done.clone()),
+        });
+    }
+
+    // Same as push for the changes sent while a slider is dragged, the whole drag is undone in one go
+    pub fn push_drag(&mut self, done: &Input, revert: Vec<Input>) {
+        if revert.is_empty() {
+            return;
+        }
+        if let (true, Some(Input::Param(i, change)), Input::Param(j, next)) = (
+            self.dragging,
+            self.undo.back().and_then(|entry| entry.done.as_ref()),
+            done,
+        ) {
+            if i == j && change.same_param(next) {
+                return;
+            }
+        }
+        self.push(done, revert);
+        self.dragging = true;
+    }
+
+    // The slider was released, the next change gets its own entry
+    pub fn end_drag(&mut self) {
+        self.dragging = false;
+    }
+
+    fn push_entry(&mut self, entry: Entry) {
+        self.undo.push_back(entry);
+        if self.undo.len() > Self::LIMIT {
+            self.undo.pop_front();
+        }
+    }
+
+    // Inputs undoing the last change, the inputs reverting them go to push_redo
+    pub fn undo(&mut self) -> Option<Vec<Input>> {
+        self.dragging = false;
+        self.undo.pop_back().map(|entry| entry.revert)
+    }
+
+    // Inputs doing the last undone change again, the inputs reverting them go to push_undo
+    pub fn redo(&mut self) -> Option<Vec<Input>> {
+        self.dragging = false;
+        self.redo.pop().map(|entry| entry.revert)
+    }
+
+    // Remember how to undo an undo (on the redo stack) or a redo (on the undo stack)
+    pub fn push_redo(&mut self, revert: Vec<Input>) {
+        self.redo.push(Entry { revert, done: None });
+    }
+
+    pub fn push_undo(&mut self, revert: Vec<Input>) {
+        self.push_entry(Entry { revert, done: None });
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.dragging = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspector::*;
+    use crate::skin::*;
+    use iced::Vector;
+
+    fn target(x: f32) -> Input {
+        Input::Target(0, Vector::new(x, 0.0))
+    }
+
+    fn speed(value: f32) -> Input {
+        Input::Param(0, ParamChange::MaxSpeed(value))
+    }
+
+    fn undo_target(history: &mut History) -> Option<f32> {
+        match history.undo()?.as_slice() {
+            [Input::Target(_, position)] => Some(position.x),
+            _ => panic!("expected a single target"),
+        }
+    }
+
+    #[test]
+    fn undo_returns_the_reverts_newest_first() {
+        let mut history = History::default();
+        history.push(&target(1.0), vec![target(0.0)]);
+        history.push(&target(2.0), vec![target(1.0)]);
+        assert_eq!(undo_target(&mut history), Some(1.0));
+        assert_eq!(undo_target(&mut history), Some(0.0));
+        assert_eq!(undo_target(&mut history), None);
+    }
+
+    #[test]
+    fn empty_reverts_are_not_remembered() {
+        let mut history = History::default();
+        history.push(&Input::CycleEdgePolicy, Vec::new());
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn redo_is_cleared_by_a_new_change() {
+        let mut history = History::default();
+        history.push(&target(1.0), vec![target(0.0)]);
+        history.undo().unwrap();
+        history.push_redo(vec![target(1.0)]);
+        assert!(matches!(
+            history.redo().as_deref(),
+            Some([Input::Target(..)])
+        ));
+        history.push_undo(vec![target(0.0)]);
+
+        history.undo().unwrap();
+        history.push_redo(vec![target(1.0)]);
+        history.push(&target(5.0), vec![target(0.0)]);
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_past_the_limit() {
+        let mut history = History::default();
+        for i in 0..History::LIMIT + 10 {
+            history.push(&target(i as f32 + 1.0), vec![target(i as f32)]);
+        }
+        let mut count = 0;
+        let mut last = None;
+        while let Some(x) = undo_target(&mut history) {
+            count += 1;
+            last = Some(x);
+        }
+        assert_eq!(count, History::LIMIT);
+        assert_eq!(last, Some(10.0));
+    }
+
+    #[test]
+    fn one_drag_is_one_entry() {
+        let mut history = History::default();
+        history.push_drag(&speed(310.0), vec![speed(300.0)]);
+        history.push_drag(&speed(320.0), vec![speed(310.0)]);
+        history.push_drag(&speed(330.0), vec![speed(320.0)]);
+        history.end_drag();
+        assert!(matches!(
+            history.undo().as_deref(),
+            Some([Input::Param(0, ParamChange::MaxSpeed(value))]) if *value == 300.0
+        ));
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn separate_drags_of_the_same_slider_are_separate_entries() {
+        let mut history = History::default();
+        history.push_drag(&speed(310.0), vec![speed(300.0)]);
+        history.end_drag();
+        history.push_drag(&speed(320.0), vec![speed(310.0)]);
+        history.end_drag();
+        assert!(history.undo().is_some());
+        assert!(history.undo().is_some());
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn hotkey_changes_are_never_merged() {
+        let mut history = History::default();
+        let pattern = |pattern: Pattern| Input::Param(0, ParamChange::Pattern(pattern));
+        history.push(
+            &pattern(Pattern::Plain.next()),
+            vec![pattern(Pattern::Plain)],
+        );
+        history.push(
+            &pattern(Pattern::Plain.next().next()),
+            vec![pattern(Pattern::Plain.next())],
+        );
+        assert!(history.undo().is_some());
+        assert!(history.undo().is_some());
+        assert!(history.undo().is_none());
+
+        // A hotkey change right after a drag doesn't join it either
+        history.push_drag(&speed(310.0), vec![speed(300.0)]);
+        history.push(&speed(500.0), vec![speed(310.0)]);
+        assert!(history.undo().is_some());
+        assert!(history.undo().is_some());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::app::*;
+use crate::skin::*;
+use crate::snake::*;
+use crate::trail::*;
+use iced::{
+    theme,
+    widget::{column, container, horizontal_rule, scrollable, slider, text, toggler, Space},
+    Color, Element, Length,
+};
+use serde::{Deserialize, Serialize};
+use std::{f32::consts::PI, ops::RangeInclusive};
+
+pub const PANEL_WIDTH: f32 = 280.0;
+
+// A change of one tunable parameter of a creature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamChange {
+    MinSpeed(f32),
+    MaxSpeed(f32),
+    TurnRate(f32),
+    VisionAngle(f32),
+    Color(#[serde(with = "crate::persist::color")] Color),
+    Radius(usize, f32),
+    Offset(usize, f32),
+    AnimateTail(bool),
+    Pattern(Pattern),
+    Gaze(Gaze),
+    Trail(Option<Trail>),
+}
+
+impl ParamChange {
+    // Apply the change to a snake, keeping min_speed <= speed <= max_speed
+    pub fn apply(&self, snake: &mut Snake) {
+        match *self {
+            Self::Pattern(ref pattern) => snake.pattern = pattern.clone(),
+            Self::Gaze(gaze) => snake.gaze = gaze,
+            Self::Trail(ref trail) => snake.trail = trail.clone(),
+            // Speeds are in pixels per second and the turn rate in radians per second
+            Self::MinSpeed(value) => {
+                snake.min_speed = value;
+                snake.max_speed = snake.max_speed.max(value);
+                snake.modify_speed(0.0);
+            }
+            Self::MaxSpeed(value) => {
+                snake.max_speed = value;
+                snake.min_speed = snake.min_speed.min(value);
+                snake.modify_speed(0.0);
+            }
+            Self::TurnRate(value) => snake.turn_rate = value,
+            Self::VisionAngle(value) => snake.vision_angle = value,
+            Self::Color(color) => snake.color = color,
+            Self::Radius(i, value) => {
+                if let Some(circle) = snake.chain.circles.get_mut(i) {
+                    circle.radius = value;
+                }
+            }
+            Self::Offset(i, value) => {
+                if let Some(circle) = snake.chain.circles.get_mut(i) {
+                    circle.offset = value;
+                }
+            }
+            Self::AnimateTail(value) => snake.animate_tail = value,
+        }
+        // Reshape the body right away, so changes are visible while paused
+        if let Self::Radius(..) | Self::Offset(..) = self {
+            snake.chain.update_positions(0);
+            snake.sync_display();
+        }
+    }
+
+    // Changes bringing back the current value, call before applying this change
+    // Both speeds are restored, since changing one of them can push the other
+    pub fn revert(&self, snake: &Snake) -> Vec<ParamChange> {
+        let circle = |i: usize| snake.chain.circles.get(i);
+        match *self {
+            Self::MinSpeed(_) | Self::MaxSpeed(_) => vec![
+                Self::MaxSpeed(snake.max_speed),
+                Self::MinSpeed(snake.min_speed),
+            ],
+            Self::TurnRate(_) => vec![Self::TurnRate(snake.turn_rate)],
+            Self::VisionAngle(_) => vec![Self::VisionAngle(snake.vision_angle)],
+            Self::Color(_) => vec![Self::Color(snake.color)],
+            Self::Radius(i, _) => circle(i)
+                .map(|c| Self::Radius(i, c.radius))
+                .into_iter()
+                .collect(),
+            Self::Offset(i, _) => circle(i)
+                .map(|c| Self::Offset(i, c.offset))
+                .into_iter()
+                .collect(),
+            Self::AnimateTail(_) => vec![Self::AnimateTail(snake.animate_tail)],
+            Self::Pattern(_) => vec![Self::Pattern(snake.pattern.clone())],
+            Self::Gaze(_) => vec![Self::Gaze(snake.gaze)],
+            Self::Trail(_) => vec![Self::Trail(snake.trail.clone())],
+        }
+    }
+
+    // Whether two changes set the same parameter, e.g. the ones sent during a slider drag
+    pub fn same_param(&self, other: &ParamChange) -> bool {
+        match (self, other) {
+            (Self::Radius(i, _), Self::Radius(j, _)) | (Self::Offset(i, _), Self::Offset(j, _)) => {
+                i == j
+            }
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+// Side panel for tuning the selected creature while the simulation runs
+#[derive(Default)]
+pub struct Inspector {
+    pub visible: bool,
+    // Index of the circle whose radius and offset are shown
+    pub circle: usize,
+}
+
+impl Inspector {
+    pub fn view<'a>(&self, snake: Option<&Snake>) -> Element<'a, MyAppMessage> {
+        let content: Element<'a, MyAppMessage> = match snake {
+            None => text("No creature selected").into(),
+            Some(snake) => {
+                let circle = self.circle.min(snake.chain.circles.len() - 1);
+                column![
+                    text("Movement").size(18),
+                    Self::param(
+                        "Min speed",
+                        snake.min_speed,
+                        10.0..=900.0,
+                        1.0,
+                        ParamChange::MinSpeed
+                    ),
+                    Self::param(
+                        "Max speed",
+                        snake.max_speed,
+                        10.0..=900.0,
+                        1.0,
+                        ParamChange::MaxSpeed
+                    ),
+                    Self::param(
+                        "Turn rate",
+                        snake.turn_rate,
+                        0.1..=6.0,
+                        0.01,
+                        ParamChange::TurnRate
+                    ),
+                    Self::param(
+                        "Vision angle",
+                        snake.vision_angle,
+                        0.05..=PI / 2.0,
+                        0.01,
+                        ParamChange::VisionAngle
+                    ),
+                    toggler(
+                        Some(String::from("Animate tail")),
+                        snake.animate_tail,
+                        |v| MyAppMessage::Param(ParamChange::AnimateTail(v))
+                    ),
+                    horizontal_rule(1),
+                    text("Color").size(18),
+                    Self::color(snake),
+                    horizontal_rule(1),
+                    text("Body").size(18),
+                    text(format!("Circle: {}", circle)).size(14),
+                    slider(
+                        0..=(snake.chain.circles.len() - 1) as u16,
+                        circle as u16,
+                        |i| MyAppMessage::InspectCircle(i as usize)
+                    ),
+                    Self::param(
+                        "Radius",
+                        snake.chain.circles[circle].radius,
+                        1.0..=100.0,
+                        0.5,
+                        move |r| ParamChange::Radius(circle, r)
+                    ),
+                    Self::param(
+                        "Offset",
+                        snake.chain.circles[circle].offset,
+                        -50.0..=50.0,
+                        0.5,
+                        move |o| ParamChange::Offset(circle, o)
+                    ),
+                ]
+                .spacing(10)
+                .into()
+            }
+        };
+
+        container(scrollable(content))
+            .width(PANEL_WIDTH)
+            .height(Length::Fill)
+            .padding(12)
+            .style(theme::Container::Box)
+            .into()
+    }
+
+    // Sliders of the body color, which only shows when the body isn't painted with a gradient
+    fn color<'a>(snake: &Snake) -> Element<'a, MyAppMessage> {
+        if !snake.colors.is_empty() {
+            return text(format!(
+                "Painted with a gradient of {} colors, set by the colors of the creature file",
+                snake.colors.len()
+            ))
+            .size(14)
+            .into();
+        }
+        let color = snake.color;
+        column![
+            container(Space::new(Length::Fill, 20.0)).style(theme::Container::from(
+                container::Appearance {
+                    background: Some(color.into()),
+                    ..Default::default()
+                }
+            )),
+            Self::param("Red", color.r, 0.0..=1.0, 0.01, move |r| {
+                ParamChange::Color(Color { r, ..color })
+            }),
+            Self::param("Green", color.g, 0.0..=1.0, 0.01, move |g| {
+                ParamChange::Color(Color { g, ..color })
+            }),
+            Self::param("Blue", color.b, 0.0..=1.0, 0.01, move |b| {
+                ParamChange::Color(Color { b, ..color })
+            }),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    // Labeled slider for a single parameter
+    fn param<'a>(
+        label: &str,
+        value: f32,
+        range: RangeInclusive<f32>,
+        step: f32,
+        change: impl Fn(f32) -> ParamChange + 'a,
+    ) -> Element<'a, MyAppMessage> {
+        column![
+            text(format!("{}: {:.3}", label, value)).size(14),
+            slider(range, value, move |v| MyAppMessage::DragParam(change(v)))
+                .step(step)
+                .on_release(MyAppMessage::ReleaseParam),
+        ]
+        .spacing(4)
+        .into()
+    }
+}
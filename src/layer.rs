@@ -0,0 +1,77 @@
+use clap::ValueEnum;
+use iced::widget::canvas::Cache;
+
+// Parts of the canvas drawn separately, each one is only redrawn after it is invalidated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Layer {
+    // Plain color filling the canvas, redrawn only when the canvas is resized
+    Background,
+    // Edges of the world and the grid, redrawn when the camera or the world change
+    Scenery,
+    // Bodies, eyes and targets of the creatures, or the editor, redrawn every frame
+    Creatures,
+    // Overlays toggled with the number keys, redrawn every frame while one of them is on
+    Debug,
+    // Banner and help text in screen coordinates, redrawn when they change
+    Hud,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [
+        Layer::Background,
+        Layer::Scenery,
+        Layer::Creatures,
+        Layer::Debug,
+        Layer::Hud,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+pub struct Layers {
+    caches: [Cache; Layer::ALL.len()],
+    // Layers from bottom to top, the missing ones are not drawn
+    order: Vec<Layer>,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self::new(Layer::ALL.to_vec())
+    }
+}
+
+impl Layers {
+    pub fn new(order: Vec<Layer>) -> Self {
+        Self {
+            caches: Default::default(),
+            order,
+        }
+    }
+
+    pub fn order(&self) -> &[Layer] {
+        &self.order
+    }
+
+    pub fn cache(&self, layer: Layer) -> &Cache {
+        &self.caches[layer.index()]
+    }
+
+    pub fn invalidate(&mut self, layer: Layer) {
+        self.caches[layer.index()].clear();
+    }
+
+    pub fn invalidate_all(&mut self) {
+        for cache in self.caches.iter_mut() {
+            cache.clear();
+        }
+    }
+
+    // Everything drawn in world coordinates, e.g. after the camera moved
+    pub fn invalidate_world(&mut self) {
+        self.invalidate(Layer::Scenery);
+        self.invalidate(Layer::Creatures);
+        self.invalidate(Layer::Debug);
+    }
+}
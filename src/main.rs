@@ -1,15 +1,61 @@
 use iced::{
+    window,
     Application, // Also import implemented Traits
     Result,
     Settings,
+    Size,
 };
 mod app;
+mod appendage;
+mod bench;
+mod camera;
 mod chain;
 mod circle;
+mod cli;
+mod contour;
+mod creature;
+mod editor;
+mod headless;
+mod history;
+mod inspector;
+mod layer;
+mod overlay;
+mod palette;
+mod persist;
+mod replay;
+mod rng;
 mod screen;
+mod sim;
+mod skin;
 mod snake;
+mod stroke;
+mod trail;
+mod watch;
+mod world;
 use crate::app::*;
+use crate::cli::*;
 
 fn main() -> Result {
-    MyApp::run(Settings::default())
+    let flags = Flags::parse_and_validate();
+    if flags.headless {
+        if let Err(error) = headless::run(&flags) {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if flags.load.is_none() && flags.replay.is_none() {
+        println!("Using seed {}", flags.seed());
+    }
+
+    let size = Size::new(flags.width as f32, flags.height as f32);
+    MyApp::run(Settings {
+        window: window::Settings {
+            size,
+            // Close through the app, so the recording can be written first
+            exit_on_close_request: flags.record.is_none(),
+            ..Default::default()
+        },
+        ..Settings::with_flags(flags)
+    })
 }
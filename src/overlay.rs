@@ -0,0 +1,58 @@
+// Debug views that can be switched on and off while the app is running
+#[derive(Debug, Clone, Copy)]
+pub enum Overlay {
+    Circles,
+    Centers,
+    Directions,
+    OutlinePoints,
+    VisionCone,
+    BlindSpots,
+    Action,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Overlays {
+    // Skeleton circles of the chain
+    pub circles: bool,
+    // Center marker of each circle
+    pub centers: bool,
+    // Direction vector of each circle
+    pub directions: bool,
+    // Points used by the outline, with their index in the contour
+    pub outline_points: bool,
+    // Field of vision of the head
+    pub vision_cone: bool,
+    // Circles where the destination can't be reached by turning
+    pub blind_spots: bool,
+    // Name of the current FSM action
+    pub action: bool,
+}
+
+impl Overlays {
+    pub fn toggle(&mut self, overlay: Overlay) {
+        let flag = match overlay {
+            Overlay::Circles => &mut self.circles,
+            Overlay::Centers => &mut self.centers,
+            Overlay::Directions => &mut self.directions,
+            Overlay::OutlinePoints => &mut self.outline_points,
+            Overlay::VisionCone => &mut self.vision_cone,
+            Overlay::BlindSpots => &mut self.blind_spots,
+            Overlay::Action => &mut self.action,
+        };
+        *flag = !*flag;
+    }
+
+    // Whether at least one overlay is on
+    pub fn any(&self) -> bool {
+        let Self {
+            circles,
+            centers,
+            directions,
+            outline_points,
+            vision_cone,
+            blind_spots,
+            action,
+        } = *self;
+        circles || centers || directions || outline_points || vision_cone || blind_spots || action
+    }
+}
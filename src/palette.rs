@@ -0,0 +1,140 @@
+use crate::persist::*;
+use clap::ValueEnum;
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Built-in palettes, each one also picks the iced theme of the widgets around the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum, Serialize, Deserialize)]
+pub enum ThemeKind {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeKind {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Dark => Self::Light,
+            Self::Light => Self::Dark,
+        }
+    }
+}
+
+// Colors of everything drawn on the canvas that isn't part of a creature's own looks
+// Palette files give channels from 0 to 255 and alpha from 0 to 1, missing colors come from the dark palette
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    // Theme of the widgets, e.g. the inspector panel
+    pub theme: ThemeKind,
+    #[serde(with = "rgba8")]
+    pub background: Color,
+    #[serde(with = "rgba8")]
+    pub grid: Color,
+    #[serde(with = "rgba8")]
+    pub world_edge: Color,
+    // Stroke around the bodies and appendages
+    #[serde(with = "rgba8")]
+    pub outline: Color,
+    // Destination marker of every creature
+    #[serde(with = "rgba8")]
+    pub target: Color,
+    #[serde(with = "rgba8")]
+    pub blind_spots: Color,
+    #[serde(with = "rgba8")]
+    pub vision_cone: Color,
+    // Thin lines of the debug overlays and of the circles in the editor
+    #[serde(with = "rgba8")]
+    pub overlay_line: Color,
+    #[serde(with = "rgba8")]
+    pub centers: Color,
+    #[serde(with = "rgba8")]
+    pub directions: Color,
+    #[serde(with = "rgba8")]
+    pub outline_points: Color,
+    // Body outline in the editor and the text next to the heads
+    #[serde(with = "rgba8")]
+    pub foreground: Color,
+    // Selected circle and outline points in the editor
+    #[serde(with = "rgba8")]
+    pub highlight: Color,
+    // Direction of the head in the editor
+    #[serde(with = "rgba8")]
+    pub heading: Color,
+    #[serde(with = "rgba8")]
+    pub banner: Color,
+    #[serde(with = "rgba8")]
+    pub banner_text: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Palette {
+    pub fn dark() -> Self {
+        Self {
+            theme: ThemeKind::Dark,
+            background: Color::from_rgb8(39, 45, 52),
+            grid: Color::from_rgba8(255, 255, 255, 0.04),
+            world_edge: Color::from_rgba8(255, 255, 255, 0.2),
+            outline: Color::WHITE,
+            target: Color::from_rgb8(252, 50, 145),
+            blind_spots: Color::from_rgba8(255, 255, 255, 0.2),
+            vision_cone: Color::from_rgba8(255, 255, 255, 0.1),
+            overlay_line: Color::from_rgba8(255, 255, 255, 0.4),
+            centers: Color::WHITE,
+            directions: Color::from_rgb8(80, 200, 255),
+            outline_points: Color::from_rgb8(255, 220, 80),
+            foreground: Color::WHITE,
+            highlight: Color::from_rgb8(255, 214, 10),
+            heading: Color::from_rgb8(10, 200, 255),
+            banner: Color::from_rgba8(170, 30, 40, 0.9),
+            banner_text: Color::WHITE,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            theme: ThemeKind::Light,
+            background: Color::from_rgb8(236, 232, 222),
+            grid: Color::from_rgba8(0, 0, 0, 0.06),
+            world_edge: Color::from_rgba8(0, 0, 0, 0.25),
+            outline: Color::from_rgb8(40, 38, 46),
+            target: Color::from_rgb8(214, 30, 120),
+            blind_spots: Color::from_rgba8(0, 0, 0, 0.12),
+            vision_cone: Color::from_rgba8(0, 0, 0, 0.06),
+            overlay_line: Color::from_rgba8(0, 0, 0, 0.4),
+            centers: Color::from_rgb8(30, 30, 36),
+            directions: Color::from_rgb8(0, 110, 190),
+            outline_points: Color::from_rgb8(200, 110, 0),
+            foreground: Color::from_rgb8(30, 30, 36),
+            highlight: Color::from_rgb8(220, 140, 0),
+            heading: Color::from_rgb8(0, 110, 190),
+            banner: Color::from_rgba8(190, 40, 50, 0.92),
+            banner_text: Color::WHITE,
+        }
+    }
+
+    pub fn preset(kind: ThemeKind) -> Self {
+        match kind {
+            ThemeKind::Dark => Self::dark(),
+            ThemeKind::Light => Self::light(),
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        match self.theme {
+            ThemeKind::Dark => Theme::Dark,
+            ThemeKind::Light => Theme::Light,
+        }
+    }
+
+    // Read a palette file
+    pub fn load(path: &Path) -> Result<Self, RonError> {
+        load_ron(path)
+    }
+}
@@ -0,0 +1,159 @@
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+// Errors for the RON files we read and write (creatures, behaviors, snapshots)
+#[derive(Debug)]
+pub enum RonError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, Box<ron::error::SpannedError>),
+    Serialize(PathBuf, ron::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for RonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "cannot access '{}': {}", path.display(), error),
+            Self::Parse(path, error) => write!(f, "{}:{}", path.display(), error),
+            Self::Serialize(path, error) => {
+                write!(f, "cannot serialize '{}': {}", path.display(), error)
+            }
+            Self::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for RonError {}
+
+// Read a RON file into any deserializable type
+pub fn load_ron<T: DeserializeOwned>(path: &Path) -> Result<T, RonError> {
+    let source = fs::read_to_string(path).map_err(|e| RonError::Io(path.to_path_buf(), e))?;
+    ron::from_str(&source).map_err(|e| RonError::Parse(path.to_path_buf(), Box::new(e)))
+}
+
+// Write any serializable value to a pretty printed RON file
+pub fn save_ron<T: Serialize>(value: &T, path: &Path) -> Result<(), RonError> {
+    let source = ron::ser::to_string_pretty(value, PrettyConfig::default())
+        .map_err(|e| RonError::Serialize(path.to_path_buf(), e))?;
+    fs::write(path, source).map_err(|e| RonError::Io(path.to_path_buf(), e))
+}
+
+// Serde helpers for the iced types we store, use them with #[serde(with = "...")]
+pub mod vector {
+    use iced::Vector;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Vector, serializer: S) -> Result<S::Ok, S::Error> {
+        (v.x, v.y).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vector, D::Error> {
+        let (x, y) = <(f32, f32)>::deserialize(deserializer)?;
+        Ok(Vector::new(x, y))
+    }
+}
+
+pub mod option_vector {
+    use iced::Vector;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Option<Vector>, serializer: S) -> Result<S::Ok, S::Error> {
+        v.map(|v| (v.x, v.y)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vector>, D::Error> {
+        let v = Option::<(f32, f32)>::deserialize(deserializer)?;
+        Ok(v.map(|(x, y)| Vector::new(x, y)))
+    }
+}
+
+pub mod color {
+    use iced::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        (c.r, c.g, c.b, c.a).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let (r, g, b, a) = <(f32, f32, f32, f32)>::deserialize(deserializer)?;
+        Ok(Color { r, g, b, a })
+    }
+}
+
+pub mod colors {
+    use iced::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(colors: &[Color], serializer: S) -> Result<S::Ok, S::Error> {
+        let colors: Vec<_> = colors.iter().map(|c| (c.r, c.g, c.b, c.a)).collect();
+        colors.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+        let colors = Vec::<(f32, f32, f32, f32)>::deserialize(deserializer)?;
+        Ok(colors
+            .into_iter()
+            .map(|(r, g, b, a)| Color { r, g, b, a })
+            .collect())
+    }
+}
+
+pub mod option_color {
+    use iced::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Option<Color>, serializer: S) -> Result<S::Ok, S::Error> {
+        c.map(|c| (c.r, c.g, c.b, c.a)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Color>, D::Error> {
+        let c = Option::<(f32, f32, f32, f32)>::deserialize(deserializer)?;
+        Ok(c.map(|(r, g, b, a)| Color { r, g, b, a }))
+    }
+}
+
+// Optional value written without Some(..), use it with default and skip_serializing_if = "Option::is_none"
+pub mod bare_option {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        T::deserialize(deserializer).map(Some)
+    }
+}
+
+// Colors written by hand, 0 to 255 channels and an alpha between 0 and 1
+pub mod rgba8 {
+    use iced::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, _] = c.into_rgba8();
+        (r, g, b, c.a).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let (r, g, b, a) = <(u8, u8, u8, f32)>::deserialize(deserializer)?;
+        Ok(Color::from_rgba8(r, g, b, a))
+    }
+}
@@ -0,0 +1,414 @@
+use crate::cli::*;
+use crate::creature::*;
+use crate::inspector::*;
+use crate::persist::*;
+use crate::sim::*;
+use crate::snake::*;
+use iced::{Size, Vector};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+// A user action that changes the simulation, recorded with the step it happened at
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Input {
+    // Send a creature towards a point
+    Target(usize, #[serde(with = "crate::persist::vector")] Vector),
+    // Pin or unpin the destination of a creature
+    Pin(
+        usize,
+        #[serde(with = "crate::persist::option_vector")] Option<Vector>,
+    ),
+    Spawn,
+    Remove(usize),
+    // Put back a removed creature, e.g. when a removal is undone
+    Insert(usize, Box<Snake>),
+    Param(usize, ParamChange),
+    CycleEdgePolicy,
+    ToggleTailAnimation,
+    // New canvas size, the world follows it
+    Resize(f32, f32),
+    Creature(Box<CreatureDef>),
+    Behavior(BehaviorDef),
+}
+
+impl Input {
+    pub fn apply(&self, sim: &mut Simulation) {
+        match self {
+            Self::Target(i, target) => {
+                if let Some(snake) = sim.snakes.get_mut(*i) {
+                    snake.set_destination(*target);
+                }
+            }
+            Self::Pin(i, target) => {
+                if let Some(snake) = sim.snakes.get_mut(*i) {
+                    snake.pin_destination(*target);
+                }
+            }
+            Self::Spawn => sim.spawn(),
+            Self::Remove(i) => {
+                if *i < sim.snakes.len() {
+                    sim.snakes.remove(*i);
+                }
+            }
+            Self::Insert(i, snake) => {
+                let mut snake = snake.as_ref().clone();
+                // Loaded from a recording the snake has no drawing chains yet
+                snake.sync_display();
+                sim.snakes.insert((*i).min(sim.snakes.len()), snake);
+            }
+            Self::Param(i, change) => {
+                if let Some(snake) = sim.snakes.get_mut(*i) {
+                    change.apply(snake);
+                }
+            }
+            Self::CycleEdgePolicy => sim.world.policy = sim.world.policy.next(),
+            Self::ToggleTailAnimation => {
+                for snake in sim.snakes.iter_mut() {
+                    snake.animate_tail = !snake.animate_tail;
+                }
+            }
+            Self::Resize(width, height) => sim.resize(Size::new(*width, *height)),
+            Self::Creature(def) => sim.reload_creature((**def).clone()),
+            Self::Behavior(def) => sim.reload_behavior(def.clone()),
+        }
+    }
+
+    // Inputs that undo this one, call before applying it
+    // Changes to the world, the files and pinned destinations can't be undone and give nothing
+    pub fn revert(&self, sim: &Simulation) -> Vec<Input> {
+        match self {
+            Self::Target(i, _) => sim
+                .snakes
+                .get(*i)
+                .map(|snake| Self::Target(*i, snake.destination))
+                .into_iter()
+                .collect(),
+            Self::Spawn => vec![Self::Remove(sim.snakes.len())],
+            Self::Remove(i) => sim
+                .snakes
+                .get(*i)
+                .map(|snake| Self::Insert(*i, Box::new(snake.clone())))
+                .into_iter()
+                .collect(),
+            Self::Insert(i, _) => vec![Self::Remove((*i).min(sim.snakes.len()))],
+            Self::Param(i, change) => match sim.snakes.get(*i) {
+                Some(snake) => change
+                    .revert(snake)
+                    .into_iter()
+                    .map(|change| Self::Param(*i, change))
+                    .collect(),
+                None => Vec::new(),
+            },
+            Self::ToggleTailAnimation => vec![Self::ToggleTailAnimation],
+            Self::Pin(..)
+            | Self::CycleEdgePolicy
+            | Self::Resize(..)
+            | Self::Creature(_)
+            | Self::Behavior(_) => Vec::new(),
+        }
+    }
+}
+
+// Everything needed to build the starting simulation again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Setup {
+    pub width: f32,
+    pub height: f32,
+    pub seed: u64,
+    pub preset: Preset,
+    pub creature: Option<CreatureDef>,
+    pub behavior: Option<BehaviorDef>,
+    pub creatures: usize,
+}
+
+impl Setup {
+    pub fn new(flags: &Flags) -> Self {
+        Self {
+            width: flags.width as f32,
+            height: flags.height as f32,
+            seed: flags.seed(),
+            preset: flags.preset,
+            creature: flags.creature_def.clone(),
+            behavior: flags.behavior_def.clone(),
+            creatures: flags.creatures,
+        }
+    }
+
+    pub fn simulation(&self) -> Simulation {
+        Simulation::new(
+            Size::new(self.width, self.height),
+            self.seed,
+            self.preset,
+            self.creature.clone(),
+            self.behavior.clone(),
+            self.creatures,
+        )
+    }
+}
+
+// A recorded session, checksums[n] is the state checksum after n steps
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub setup: Setup,
+    pub inputs: Vec<(u64, Input)>,
+    pub checksums: Vec<u64>,
+}
+
+// FNV-1a hash of the simulation state
+// Written by hand because the standard hasher is allowed to change between Rust versions
+pub struct StateHash(u64);
+
+impl StateHash {
+    pub fn new() -> Self {
+        Self(0xCBF2_9CE4_8422_2325)
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    // Hash the exact bits, the smallest floating-point difference changes the checksum
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_bits().to_le_bytes());
+    }
+
+    pub fn write_vector(&mut self, value: Vector) {
+        self.write_f32(value.x);
+        self.write_f32(value.y);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// Records the inputs of a session and the checksum of every step
+pub struct Recorder {
+    recording: Recording,
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(setup: Setup, path: &Path, sim: &Simulation) -> Self {
+        Self {
+            recording: Recording {
+                setup,
+                inputs: Vec::new(),
+                checksums: vec![sim.checksum()],
+            },
+            path: path.to_path_buf(),
+        }
+    }
+
+    // Call before applying the input, so it gets the step it will be replayed at
+    pub fn record(&mut self, sim: &Simulation, input: &Input) {
+        self.recording.inputs.push((sim.steps, input.clone()));
+    }
+
+    // Call after every simulation step
+    pub fn step(&mut self, sim: &Simulation) {
+        self.recording.checksums.push(sim.checksum());
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self) -> Result<(), RonError> {
+        save_ron(&self.recording, &self.path)
+    }
+}
+
+// First step where the replayed state doesn't match the recording
+#[derive(Debug)]
+pub struct Divergence {
+    pub step: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "replay diverged at step {}: expected checksum {:016x}, got {:016x}",
+            self.step, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+// Feeds the recorded inputs back into a simulation and checks every step against the recording
+pub struct Replayer {
+    recording: Recording,
+    // Index of the next input to apply
+    next: usize,
+}
+
+impl Replayer {
+    pub fn load(path: &Path) -> Result<Self, RonError> {
+        let recording: Recording = load_ron(path)?;
+        if recording.checksums.is_empty() {
+            return Err(RonError::Invalid(
+                path.to_path_buf(),
+                String::from("the recording has no checksums"),
+            ));
+        }
+        Ok(Self { recording, next: 0 })
+    }
+
+    // The simulation the recording started from
+    pub fn simulation(&self) -> Simulation {
+        self.recording.setup.simulation()
+    }
+
+    // Number of recorded steps
+    pub fn steps(&self) -> u64 {
+        self.recording.checksums.len() as u64 - 1
+    }
+
+    pub fn finished(&self, sim: &Simulation) -> bool {
+        sim.steps >= self.steps()
+    }
+
+    // Apply the inputs recorded at the current step, call before every simulation step
+    pub fn feed(&mut self, sim: &mut Simulation) {
+        while let Some((step, input)) = self.recording.inputs.get(self.next) {
+            if *step > sim.steps {
+                break;
+            }
+            input.apply(sim);
+            self.next += 1;
+        }
+    }
+
+    // Compare the state with the recorded checksum of the same step
+    pub fn check(&self, sim: &Simulation) -> Result<(), Divergence> {
+        let Some(&expected) = self.recording.checksums.get(sim.steps as usize) else {
+            return Ok(());
+        };
+        let actual = sim.checksum();
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(Divergence {
+                step: sim.steps,
+                expected,
+                actual,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::SIM_DT;
+
+    fn setup(seed: u64) -> Setup {
+        Setup {
+            width: 800.0,
+            height: 600.0,
+            seed,
+            preset: Preset::Slick,
+            creature: None,
+            behavior: None,
+            creatures: 3,
+        }
+    }
+
+    fn run(sim: &mut Simulation, steps: u64) {
+        for _ in 0..steps {
+            sim.update(SIM_DT);
+        }
+    }
+
+    #[test]
+    fn state_hash_is_fnv1a() {
+        assert_eq!(StateHash::new().finish(), 0xCBF2_9CE4_8422_2325);
+        let mut hash = StateHash::new();
+        hash.write_bytes(b"a");
+        assert_eq!(hash.finish(), 0xAF63_DC4C_8601_EC8C);
+    }
+
+    #[test]
+    fn same_seed_gives_same_checksums() {
+        let mut a = setup(7).simulation();
+        let mut b = setup(7).simulation();
+        assert_eq!(a.checksum(), b.checksum());
+        for _ in 0..300 {
+            a.update(SIM_DT);
+            b.update(SIM_DT);
+            assert_eq!(a.checksum(), b.checksum());
+        }
+        let mut other = setup(8).simulation();
+        run(&mut other, 300);
+        assert_ne!(a.checksum(), other.checksum());
+    }
+
+    // Record a session with inputs, then feed the recording to a new simulation step by step
+    fn record(steps: u64) -> (Recording, Simulation) {
+        let setup = setup(42);
+        let mut sim = setup.simulation();
+        let mut recorder = Recorder::new(setup, Path::new("unused.ron"), &sim);
+        let inputs = [
+            (20, Input::Target(0, Vector::new(100.0, -50.0))),
+            (45, Input::Pin(1, Some(Vector::new(-200.0, 80.0)))),
+            (60, Input::Spawn),
+            (90, Input::CycleEdgePolicy),
+            (120, Input::Remove(1)),
+            (150, Input::Param(0, ParamChange::MaxSpeed(400.0))),
+            (180, Input::Resize(640.0, 480.0)),
+        ];
+        for step in 0..steps {
+            for (_, input) in inputs.iter().filter(|(at, _)| *at == step) {
+                recorder.record(&sim, input);
+                input.apply(&mut sim);
+            }
+            sim.update(SIM_DT);
+            recorder.step(&sim);
+        }
+        (recorder.recording, sim)
+    }
+
+    fn replay(recording: Recording) -> Result<Simulation, Divergence> {
+        let mut replayer = Replayer { recording, next: 0 };
+        let mut sim = replayer.simulation();
+        replayer.check(&sim)?;
+        while !replayer.finished(&sim) {
+            replayer.feed(&mut sim);
+            sim.update(SIM_DT);
+            replayer.check(&sim)?;
+        }
+        Ok(sim)
+    }
+
+    #[test]
+    fn replay_matches_recording() {
+        let (recording, recorded) = record(240);
+        assert_eq!(recording.checksums.len(), 241);
+        let replayed = replay(recording).unwrap();
+        assert_eq!(replayed.steps, 240);
+        assert_eq!(replayed.checksum(), recorded.checksum());
+    }
+
+    #[test]
+    fn replay_reports_first_divergence() {
+        let (mut recording, _) = record(100);
+        recording.checksums[37] ^= 1;
+        let divergence = replay(recording).err().unwrap();
+        assert_eq!(divergence.step, 37);
+        assert_ne!(divergence.expected, divergence.actual);
+    }
+}
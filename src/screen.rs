@@ -1,34 +1,659 @@
+use crate::app::*;
+use crate::camera::*;
+use crate::creature::*;
+use crate::editor::*;
+use crate::history::*;
+use crate::inspector::*;
+use crate::layer::*;
+use crate::overlay::*;
+use crate::palette::*;
+use crate::replay::*;
+use crate::sim::*;
 use crate::snake::*;
+use crate::trail::*;
 
 use iced::{
-    mouse,
-    widget::canvas::{Cache, Geometry, Program},
-    Color, Point, Rectangle, Renderer, Theme,
+    keyboard, mouse,
+    widget::canvas::{event, Event, Frame, Geometry, Path, Program, Stroke, Text},
+    Point, Rectangle, Renderer, Size, Theme, Vector,
 };
+use std::path::Path as FilePath;
 
 pub struct Screen {
-    cache: Cache,
-    snake: Snake,
+    layers: Layers,
+    palette: Palette,
+    sim: Simulation,
+    camera: Camera,
+    overlays: Overlays,
+    selected: usize,
+    // Last known cursor offset from the canvas center
+    cursor: Option<Vector>,
+    // The selected creature chases the cursor
+    follow_cursor: bool,
+    // Error shown on top of the canvas, e.g. a creature file that failed to reload
+    banner: Option<String>,
+    // Inputs are recorded while this is set
+    recorder: Option<Recorder>,
+    // Inputs come from a recording while this is set, the user's own are ignored
+    replay: Option<Replayer>,
+    // Body editor shown instead of the simulation, which is paused while it is open
+    editor: Option<Editor>,
+    history: History,
+}
+
+// Canvas state kept by iced between events
+#[derive(Default)]
+pub struct Interaction {
+    // Last cursor position while panning with the right mouse button
+    drag: Option<Point>,
+    // Circle dragged in the editor
+    moving: Option<usize>,
+    modifiers: keyboard::Modifiers,
 }
 
 impl Screen {
-    pub fn new() -> Self {
+    // layers are drawn from bottom to top
+    pub fn new(sim: Simulation, layers: Vec<Layer>, palette: Palette) -> Self {
         Self {
-            cache: Cache::new(),
-            snake: Snake::new(),
+            layers: Layers::new(layers),
+            palette,
+            sim,
+            camera: Camera::default(),
+            overlays: Overlays::default(),
+            selected: 0,
+            cursor: None,
+            follow_cursor: false,
+            banner: None,
+            recorder: None,
+            replay: None,
+            editor: None,
+            history: History::default(),
+        }
+    }
+
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    pub fn replay(&mut self, replay: Replayer) {
+        self.replay = Some(replay);
+    }
+
+    // Every change of the simulation made by the user goes through here, so it can be recorded
+    fn input(&mut self, input: Input) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(&self.sim, &input);
+        }
+        input.apply(&mut self.sim);
+        // Creatures might have been removed
+        self.selected = self.selected.min(self.sim.snakes.len().saturating_sub(1));
+        self.layers.invalidate_world();
+    }
+
+    // An input that can be undone, e.g. a target placement or a parameter tweak
+    fn command(&mut self, input: Input) {
+        if self.replay.is_some() {
+            return;
+        }
+        let revert = input.revert(&self.sim);
+        self.history.push(&input, revert);
+        self.input(input);
+    }
+
+    // A change sent while a slider is dragged, undone together with the rest of the drag
+    fn drag_command(&mut self, input: Input) {
+        if self.replay.is_some() {
+            return;
+        }
+        let revert = input.revert(&self.sim);
+        self.history.push_drag(&input, revert);
+        self.input(input);
+    }
+
+    // Apply inputs taken from the history, returning the inputs that revert them
+    fn apply_history(&mut self, inputs: Vec<Input>) -> Vec<Input> {
+        let mut revert: Vec<Input> = Vec::new();
+        for input in inputs {
+            // The last input applied has to be reverted first
+            let mut undo = input.revert(&self.sim);
+            undo.append(&mut revert);
+            revert = undo;
+            self.input(input);
+        }
+        revert
+    }
+
+    pub fn undo(&mut self) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(inputs) = self.history.undo() {
+            let redo = self.apply_history(inputs);
+            self.history.push_redo(redo);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(inputs) = self.history.redo() {
+            let undo = self.apply_history(inputs);
+            self.history.push_undo(undo);
+        }
+    }
+
+    // Advance the simulation by a time step of dt seconds
+    pub fn update(&mut self, dt: f32) {
+        if self.editor.is_some() {
+            return;
+        }
+        if let Some(replay) = self.replay.as_mut() {
+            replay.feed(&mut self.sim);
+        } else if self.follow_cursor {
+            // The camera might have moved, so convert the cursor every step
+            let target = self.cursor.map(|offset| self.cursor_to_world(offset));
+            if let Some(snake) = self.sim.snakes.get(self.selected) {
+                if snake.pinned != target {
+                    self.input(Input::Pin(self.selected, target));
+                }
+            }
+        }
+        self.sim.update(dt);
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.step(&self.sim);
+        }
+        if let Some(replay) = &self.replay {
+            match replay.check(&self.sim) {
+                Err(divergence) => {
+                    self.replay = None;
+                    self.show_error(divergence.to_string());
+                }
+                Ok(()) if replay.finished(&self.sim) => {
+                    println!("Replayed {} steps, every checksum matches", self.sim.steps);
+                    self.replay = None;
+                }
+                Ok(()) => {}
+            }
+        }
+    }
+
+    // Write the recording, if there is one
+    pub fn save_recording(&self) {
+        if let Some(recorder) = &self.recorder {
+            match recorder.save() {
+                Ok(()) => println!("Saved recording '{}'", recorder.path().display()),
+                Err(error) => eprintln!("error: {}", error),
+            }
+        }
+    }
+
+    // Run one step and draw it as it is, without blending it with the previous one
+    pub fn step(&mut self, dt: f32) {
+        self.update(dt);
+        self.sim.sync_display();
+    }
+
+    // Prepare a new frame, alpha is how far we are between the last two time steps
+    // frame_dt is the real time since the last frame, used for smoothing the camera
+    pub fn render(&mut self, alpha: f32, frame_dt: f32) {
+        for snake in self.sim.snakes.iter_mut() {
+            snake.interpolate(alpha);
+        }
+        if self.camera.follow {
+            if let Some(snake) = self.sim.snakes.get(self.selected) {
+                self.camera
+                    .track(snake.display.circles[0].position, frame_dt);
+                self.layers.invalidate(Layer::Scenery);
+            }
+        }
+
+        // The creatures move every frame, the other layers wait until something changes them
+        self.layers.invalidate(Layer::Creatures);
+        if self.overlays.any() {
+            self.layers.invalidate(Layer::Debug);
+        }
+    }
+
+    // Follow the size of the canvas, making sure the snakes stay visible
+    pub fn resize(&mut self, size: Size) {
+        self.input(Input::Resize(size.width, size.height));
+    }
+
+    pub fn sim(&self) -> &Simulation {
+        &self.sim
+    }
+
+    pub fn reload_creature(&mut self, def: CreatureDef) {
+        self.input(Input::Creature(Box::new(def)));
+    }
+
+    pub fn reload_behavior(&mut self, def: BehaviorDef) {
+        self.input(Input::Behavior(def));
+    }
+
+    // Add a creature at a random point of the world
+    pub fn spawn(&mut self) {
+        self.command(Input::Spawn);
+    }
+
+    // Remove the selected circle in the editor, or else the selected creature
+    pub fn delete(&mut self) {
+        if self.editor.is_some() {
+            self.edit(&Edit::Remove);
+        } else if self.selected < self.sim.snakes.len() {
+            self.command(Input::Remove(self.selected));
+        }
+    }
+
+    // Swap in a simulation read from a snapshot, its world is fitted to the canvas like after a resize
+    pub fn replace_sim(&mut self, sim: Simulation, size: Size) {
+        if self.recorder.is_some() || self.replay.is_some() {
+            self.show_error(String::from(
+                "snapshots can't be loaded while recording or replaying",
+            ));
+            return;
+        }
+        self.sim = sim;
+        self.history.clear();
+        self.selected = self.selected.min(self.sim.snakes.len().saturating_sub(1));
+        self.follow_cursor = false;
+        self.resize(size);
+    }
+
+    pub fn show_error(&mut self, message: String) {
+        self.banner = Some(message);
+        self.layers.invalidate(Layer::Hud);
+    }
+
+    pub fn dismiss_error(&mut self) {
+        self.banner = None;
+        self.layers.invalidate(Layer::Hud);
+    }
+
+    // Switch to the next edge policy of the world
+    pub fn cycle_edge_policy(&mut self) {
+        self.input(Input::CycleEdgePolicy);
+    }
+
+    // Convert a cursor offset from the canvas center into a point of the world
+    fn cursor_to_world(&self, offset: Vector) -> Vector {
+        self.sim.world.clamp_point(self.camera.to_world(offset))
+    }
+
+    // Send the selected creature towards the clicked point
+    pub fn set_target(&mut self, offset: Vector) {
+        let target = self.cursor_to_world(offset);
+        if self.selected < self.sim.snakes.len() {
+            self.command(Input::Target(self.selected, target));
+        }
+    }
+
+    pub fn move_cursor(&mut self, offset: Option<Vector>) {
+        self.cursor = offset;
+        // Pupils following the cursor move even while the simulation is paused
+        if self
+            .sim
+            .snakes
+            .iter()
+            .any(|snake| snake.gaze == Gaze::Cursor)
+        {
+            self.layers.invalidate(Layer::Creatures);
+        }
+    }
+
+    pub fn toggle_follow_cursor(&mut self) {
+        self.follow_cursor = !self.follow_cursor;
+        if !self.follow_cursor {
+            self.unpin_selected();
         }
     }
 
-    pub fn update(&mut self) {
-        self.snake.update();
+    fn unpin_selected(&mut self) {
+        let pinned = self.sim.snakes.get(self.selected).map(|snake| snake.pinned);
+        if let Some(Some(_)) = pinned {
+            self.input(Input::Pin(self.selected, None));
+        }
+    }
+
+    // Only the selected creature follows the cursor
+    pub fn select_next(&mut self) {
+        self.unpin_selected();
+        self.selected = (self.selected + 1) % self.sim.snakes.len().max(1);
+    }
+
+    pub fn selected_snake(&self) -> Option<&Snake> {
+        self.sim.snakes.get(self.selected)
+    }
+
+    // Tune a parameter of the selected creature
+    pub fn apply_param(&mut self, change: &ParamChange) {
+        if self.selected < self.sim.snakes.len() {
+            self.command(Input::Param(self.selected, change.clone()));
+        }
+    }
+
+    // Tune a parameter of the selected creature with a slider, until end_drag
+    pub fn drag_param(&mut self, change: &ParamChange) {
+        if self.selected < self.sim.snakes.len() {
+            self.drag_command(Input::Param(self.selected, change.clone()));
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.history.end_drag();
+    }
+
+    // Open the editor on a copy of the selected creature, or close it
+    pub fn toggle_editor(&mut self) {
+        self.editor = match self.editor {
+            Some(_) => None,
+            None => self.selected_snake().map(Editor::new),
+        };
+        self.layers.invalidate_world();
+        self.layers.invalidate(Layer::Hud);
+    }
+
+    pub fn edit(&mut self, edit: &Edit) {
+        if let Some(editor) = self.editor.as_mut() {
+            editor.apply(edit);
+            self.layers.invalidate(Layer::Creatures);
+        }
+    }
+
+    // Write the edited creature as a definition file and as builder code
+    pub fn export(&mut self, path: &FilePath) {
+        let Some(editor) = &self.editor else {
+            self.show_error(String::from("open the editor (E) to export a creature"));
+            return;
+        };
+        match editor.export(path) {
+            Ok(code_path) => println!(
+                "Exported '{}' and '{}'",
+                path.display(),
+                code_path.display()
+            ),
+            Err(error) => self.show_error(error.to_string()),
+        }
+    }
+
+    pub fn toggle_overlay(&mut self, overlay: Overlay) {
+        self.overlays.toggle(overlay);
+        self.layers.invalidate(Layer::Debug);
+    }
+
+    // Switch the selected creature to the next skin pattern
+    pub fn cycle_pattern(&mut self) {
+        if let Some(snake) = self.selected_snake() {
+            let pattern = snake.pattern.next();
+            self.apply_param(&ParamChange::Pattern(pattern));
+        }
+    }
+
+    // Make the pupils of the selected creature follow the cursor or its destination
+    pub fn toggle_gaze(&mut self) {
+        if let Some(snake) = self.selected_snake() {
+            let gaze = match snake.gaze {
+                Gaze::Destination => Gaze::Cursor,
+                Gaze::Cursor => Gaze::Destination,
+            };
+            self.apply_param(&ParamChange::Gaze(gaze));
+        }
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    // Switch between the dark and light palettes, a custom palette is replaced by the preset after its theme
+    pub fn toggle_palette(&mut self) {
+        self.palette = Palette::preset(self.palette.theme.next());
+        self.layers.invalidate_all();
+    }
+
+    // Switch the trail of the selected creature between a ribbon, dots and none
+    pub fn cycle_trail(&mut self) {
+        if let Some(snake) = self.selected_snake() {
+            let trail = Trail::next(&snake.trail);
+            self.apply_param(&ParamChange::Trail(trail));
+        }
+    }
+
+    pub fn toggle_tail_animation(&mut self) {
+        self.command(Input::ToggleTailAnimation);
+    }
 
-        // // Clear the cache to redraw the canvas
-        self.cache.clear();
+    pub fn toggle_follow(&mut self) {
+        self.camera.follow = !self.camera.follow;
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.camera.reset();
+        self.layers.invalidate_world();
+    }
+
+    pub fn zoom(&mut self, factor: f32, offset: Vector) {
+        self.camera.zoom_at(factor, offset);
+        self.layers.invalidate_world();
+    }
+
+    pub fn pan(&mut self, delta: Vector) {
+        self.camera.pan(delta);
+        self.layers.invalidate_world();
     }
 }
 
-impl<Message> Program<Message> for Screen {
-    type State = ();
+impl Screen {
+    // Draw in world coordinates, through the camera
+    fn in_world(&self, frame: &mut Frame, draw: impl FnOnce(&mut Frame)) {
+        frame.with_save(|frame| {
+            self.camera.apply(frame);
+            draw(frame);
+        });
+    }
+
+    // Faint grid and the edges of the world
+    fn draw_scenery(&self, frame: &mut Frame) {
+        let palette = &self.palette;
+        const GRID: f32 = 100.0;
+        let world = &self.sim.world;
+        let (x, y) = (world.half_width, world.half_height);
+        let grid = Path::new(|builder| {
+            for k in 1..=(x / GRID) as i32 {
+                for column in [-k as f32 * GRID, k as f32 * GRID] {
+                    builder.move_to(Point::new(column, -y));
+                    builder.line_to(Point::new(column, y));
+                }
+            }
+            for k in 1..=(y / GRID) as i32 {
+                for row in [-k as f32 * GRID, k as f32 * GRID] {
+                    builder.move_to(Point::new(-x, row));
+                    builder.line_to(Point::new(x, row));
+                }
+            }
+            builder.move_to(Point::new(0.0, -y));
+            builder.line_to(Point::new(0.0, y));
+            builder.move_to(Point::new(-x, 0.0));
+            builder.line_to(Point::new(x, 0.0));
+        });
+        frame.stroke(
+            &grid,
+            Stroke {
+                style: palette.grid.into(),
+                width: 1.0,
+                ..Default::default()
+            },
+        );
+        frame.stroke(
+            &Path::rectangle(Point::new(-x, -y), Size::new(2.0 * x, 2.0 * y)),
+            Stroke {
+                style: palette.world_edge.into(),
+                width: 2.0,
+                ..Default::default()
+            },
+        );
+    }
+
+    // Editor controls in the top left corner of the canvas
+    fn draw_editor_help(frame: &mut Frame, palette: &Palette) {
+        frame.fill_text(Text {
+            content: String::from(
+                "Editor: click empty space to add a circle, drag to move, wheel to resize, \
+                 Shift+wheel for the offset, click a rim to add/remove an outline point, \
+                 Delete removes the selected circle, Ctrl+E exports, E closes",
+            ),
+            position: Point::new(10.0, frame.height() - 24.0),
+            color: palette.foreground,
+            size: 14.0.into(),
+            ..Default::default()
+        });
+    }
+
+    // Canvas events while the editor is open, None lets the usual controls handle them
+    fn editor_event(
+        editor: &Editor,
+        camera: &Camera,
+        state: &mut Interaction,
+        event: &Event,
+        offset: Vector,
+    ) -> Option<MyAppMessage> {
+        let point = camera.to_world(offset);
+        let tolerance = Editor::TOLERANCE / camera.zoom;
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                Some(MyAppMessage::Edit(match editor.hit(point, tolerance) {
+                    Some(Hit::Rim(i, angle)) => Edit::ToggleAngle(i, angle),
+                    Some(Hit::Inside(i)) => {
+                        state.moving = Some(i);
+                        Edit::Select(i)
+                    }
+                    None => Edit::Append(point),
+                }))
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                state.moving = None;
+                None
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => state
+                .moving
+                .map(|i| MyAppMessage::Edit(Edit::Move(i, point))),
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => *y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / 50.0,
+                };
+                let i = match editor.hit(point, tolerance)? {
+                    Hit::Rim(i, _) | Hit::Inside(i) => i,
+                };
+                Some(MyAppMessage::Edit(match state.modifiers.shift() {
+                    true => Edit::Offset(i, lines),
+                    false => Edit::Radius(i, lines),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    // Error banner along the top edge of the canvas
+    fn draw_banner(frame: &mut Frame, message: &str, palette: &Palette) {
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            Size::new(frame.width(), 32.0),
+            palette.banner,
+        );
+        frame.fill_text(Text {
+            content: format!("{}  (Esc to dismiss)", message),
+            position: Point::new(10.0, 8.0),
+            color: palette.banner_text,
+            size: 16.0.into(),
+            ..Default::default()
+        });
+    }
+}
+
+impl Program<MyAppMessage> for Screen {
+    type State = Interaction;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<MyAppMessage>) {
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            state.modifiers = modifiers;
+        }
+        let Some(position) = cursor.position_in(bounds) else {
+            // Stop panning and following if the cursor leaves the canvas
+            state.drag = None;
+            state.moving = None;
+            return match (event, self.cursor) {
+                (Event::Mouse(mouse::Event::CursorLeft), _)
+                | (Event::Mouse(mouse::Event::CursorMoved { .. }), Some(_)) => (
+                    event::Status::Ignored,
+                    Some(MyAppMessage::CursorMoved(None)),
+                ),
+                _ => (event::Status::Ignored, None),
+            };
+        };
+        // Cursor offset from the center of the canvas, the camera works with these offsets
+        let offset = position - Point::new(bounds.width / 2.0, bounds.height / 2.0);
+        if let Some(editor) = &self.editor {
+            if let Some(message) = Self::editor_event(editor, &self.camera, state, &event, offset) {
+                return (event::Status::Captured, Some(message));
+            }
+            if state.moving.is_some() {
+                return (event::Status::Captured, None);
+            }
+        }
+
+        match event {
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / 50.0,
+                };
+                (
+                    event::Status::Captured,
+                    Some(MyAppMessage::Zoom {
+                        factor: 1.1_f32.powf(lines),
+                        offset,
+                    }),
+                )
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => (
+                event::Status::Captured,
+                Some(MyAppMessage::SetTarget(offset)),
+            ),
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                state.drag = Some(position);
+                (event::Status::Captured, None)
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Right)) => {
+                state.drag = None;
+                (event::Status::Captured, None)
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => match state.drag {
+                Some(last) => {
+                    state.drag = Some(position);
+                    (
+                        event::Status::Captured,
+                        Some(MyAppMessage::Pan(position - last)),
+                    )
+                }
+                None => (
+                    event::Status::Ignored,
+                    Some(MyAppMessage::CursorMoved(Some(offset))),
+                ),
+            },
+            _ => (event::Status::Ignored, None),
+        }
+    }
 
     fn draw(
         &self,
@@ -38,14 +663,81 @@ impl<Message> Program<Message> for Screen {
         bounds: Rectangle,
         _cursor: mouse::Cursor,
     ) -> Vec<Geometry> {
-        // Closure gets updated only when we clear the cache in update()
-        let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
-            // Drawing the background
-            frame.fill_rectangle(Point::ORIGIN, bounds.size(), Color::from_rgb8(39, 45, 52));
+        // Every closure runs again only after its layer was invalidated, or when the canvas is resized
+        self.layers
+            .order()
+            .iter()
+            .map(|layer| {
+                self.layers
+                    .cache(*layer)
+                    .draw(renderer, bounds.size(), |frame| match layer {
+                        Layer::Background => {
+                            frame.fill_rectangle(
+                                Point::ORIGIN,
+                                bounds.size(),
+                                self.palette.background,
+                            );
+                        }
+                        Layer::Scenery => self.in_world(frame, |frame| self.draw_scenery(frame)),
+                        Layer::Creatures => self.in_world(frame, |frame| match &self.editor {
+                            Some(editor) => editor.draw(frame, &self.palette),
+                            None => {
+                                let cursor = self.cursor.map(|offset| self.cursor_to_world(offset));
+                                for snake in self.sim.snakes.iter() {
+                                    snake.draw(frame, cursor, &self.palette);
+                                }
+                            }
+                        }),
+                        Layer::Debug if self.editor.is_none() => self.in_world(frame, |frame| {
+                            for snake in self.sim.snakes.iter() {
+                                snake.draw_overlays(frame, &self.overlays, &self.palette);
+                            }
+                        }),
+                        Layer::Debug => {}
+                        Layer::Hud => {
+                            if self.editor.is_some() {
+                                Self::draw_editor_help(frame, &self.palette);
+                            }
+                            if let Some(message) = &self.banner {
+                                Self::draw_banner(frame, message, &self.palette);
+                            }
+                        }
+                    })
+            })
+            .collect()
+    }
 
-            self.snake.draw(frame);
-        });
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        _bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        match state.drag {
+            Some(_) => mouse::Interaction::Grabbing,
+            None => mouse::Interaction::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
 
-        vec![geometry]
+    #[test]
+    fn loaded_snapshots_fit_the_canvas() {
+        let saved = Simulation::new(Size::new(400.0, 300.0), 4, Preset::Chunky, None, None, 2);
+        let path = std::env::temp_dir().join(format!("resize-test-{}.ron", std::process::id()));
+        saved.save(&path).unwrap();
+        let loaded = Simulation::load(&path);
+        fs::remove_file(&path).ok();
+        let size = Size::new(1200.0, 900.0);
+        let sim = Simulation::new(size, 4, Preset::Chunky, None, None, 2);
+        let mut screen = Screen::new(sim, Vec::new(), Palette::default());
+        screen.replace_sim(loaded.unwrap(), size);
+        let world = &screen.sim().world;
+        assert_eq!((world.half_width, world.half_height), (600.0, 450.0));
+        assert_eq!(screen.sim().steps, saved.steps);
     }
 }
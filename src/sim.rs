@@ -0,0 +1,180 @@
+use crate::creature::*;
+use crate::persist::*;
+use crate::replay::*;
+use crate::rng::*;
+use crate::snake::*;
+use crate::world::*;
+use iced::Size;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Everything that gets simulated, independent of how it is drawn
+// Saved as a whole into snapshot files, including the state of every random generator
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    pub world: World,
+    pub snakes: Vec<Snake>,
+    pub preset: Preset,
+    // Creature loaded from a definition file, spawned instead of the preset
+    pub creature: Option<CreatureDef>,
+    // Behavior loaded from a file, overrides the behavior of every creature
+    pub behavior: Option<BehaviorDef>,
+    // Number of fixed steps simulated so far
+    pub steps: u64,
+    // Generator used for spawning, each snake gets its own generator split from this one
+    rng: SimRng,
+}
+
+impl Simulation {
+    pub fn new(
+        size: Size,
+        seed: u64,
+        preset: Preset,
+        creature: Option<CreatureDef>,
+        behavior: Option<BehaviorDef>,
+        creatures: usize,
+    ) -> Self {
+        let mut world = World::default();
+        world.resize(size);
+        let mut sim = Self {
+            world,
+            snakes: Vec::new(),
+            preset,
+            creature,
+            behavior,
+            steps: 0,
+            rng: SimRng::new(seed),
+        };
+        for _ in 0..creatures {
+            sim.spawn();
+        }
+        sim
+    }
+
+    // Add a new snake at a random point of the world
+    pub fn spawn(&mut self) {
+        let rng = self.rng.fork();
+        let mut snake = match &self.creature {
+            Some(def) => def.spawn(&self.world, rng),
+            None => Snake::new(&self.world, self.preset, rng),
+        };
+        if let Some(behavior) = &self.behavior {
+            behavior.apply(&mut snake);
+        }
+        self.snakes.push(snake);
+    }
+
+    // Use a new creature definition, every snake gets rebuilt in place
+    pub fn reload_creature(&mut self, def: CreatureDef) {
+        for snake in self.snakes.iter_mut() {
+            def.rebuild(snake);
+            if let Some(behavior) = &self.behavior {
+                behavior.apply(snake);
+            }
+        }
+        self.creature = Some(def);
+    }
+
+    // Use a new behavior for every snake
+    pub fn reload_behavior(&mut self, behavior: BehaviorDef) {
+        for snake in self.snakes.iter_mut() {
+            behavior.apply(snake);
+        }
+        self.behavior = Some(behavior);
+    }
+
+    // Write the whole simulation to a snapshot file
+    pub fn save(&self, path: &Path) -> Result<(), RonError> {
+        save_ron(self, path)
+    }
+
+    // Read a snapshot file, the simulation continues exactly where it was saved
+    pub fn load(path: &Path) -> Result<Self, RonError> {
+        let mut sim: Self = load_ron(path)?;
+        if sim.snakes.iter().any(|snake| snake.chain.circles.len() < 2) {
+            return Err(RonError::Invalid(
+                path.to_path_buf(),
+                String::from("every creature needs at least 2 circles"),
+            ));
+        }
+        sim.sync_display();
+        Ok(sim)
+    }
+
+    // Draw every creature where it is now, e.g. after loading or stepping
+    pub fn sync_display(&mut self) {
+        for snake in self.snakes.iter_mut() {
+            snake.sync_display();
+        }
+    }
+
+    // Checksum of everything that affects the next steps, used to verify replays
+    pub fn checksum(&self) -> u64 {
+        let mut hash = StateHash::new();
+        hash.write_u64(self.steps);
+        hash.write_u64(self.rng.state());
+        hash.write_f32(self.world.half_width);
+        hash.write_f32(self.world.half_height);
+        hash.write_bytes(format!("{:?}", self.world.policy).as_bytes());
+        for snake in self.snakes.iter() {
+            snake.hash_state(&mut hash);
+        }
+        hash.finish()
+    }
+
+    // Advance every snake by dt seconds
+    pub fn update(&mut self, dt: f32) {
+        for snake in self.snakes.iter_mut() {
+            snake.update(&self.world, dt);
+        }
+        self.steps += 1;
+    }
+
+    // Fit the world to a new canvas size, making sure the snakes stay visible
+    pub fn resize(&mut self, size: Size) {
+        self.world.resize(size);
+        for snake in self.snakes.iter_mut() {
+            snake.fit_to_world(&self.world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::SIM_DT;
+    use std::fs;
+
+    #[test]
+    fn snapshot_continues_like_a_straight_run() {
+        let mut sim = Simulation::new(Size::new(800.0, 600.0), 3, Preset::Chunky, None, None, 2);
+        for _ in 0..100 {
+            sim.update(SIM_DT);
+        }
+        let path = std::env::temp_dir().join(format!("snapshot-test-{}.ron", std::process::id()));
+        sim.save(&path).unwrap();
+        let loaded = Simulation::load(&path);
+        fs::remove_file(&path).ok();
+        let mut loaded = loaded.unwrap();
+        assert_eq!(loaded.steps, sim.steps);
+        assert_eq!(loaded.checksum(), sim.checksum());
+        for _ in 0..200 {
+            sim.update(SIM_DT);
+            loaded.update(SIM_DT);
+            assert_eq!(loaded.checksum(), sim.checksum());
+        }
+    }
+
+    #[test]
+    fn spawning_after_a_snapshot_is_deterministic() {
+        let mut sim = Simulation::new(Size::new(800.0, 600.0), 9, Preset::Fish, None, None, 1);
+        let path = std::env::temp_dir().join(format!("spawn-test-{}.ron", std::process::id()));
+        sim.save(&path).unwrap();
+        let loaded = Simulation::load(&path);
+        fs::remove_file(&path).ok();
+        let mut loaded = loaded.unwrap();
+        sim.spawn();
+        loaded.spawn();
+        assert_eq!(loaded.checksum(), sim.checksum());
+    }
+}
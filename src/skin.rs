@@ -0,0 +1,355 @@
+use crate::chain::*;
+use iced::{
+    widget::canvas::{path::Builder, Path},
+    Color, Point, Vector,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// Parameterization of the body: u is the distance along the spine from the head center,
+// v goes across the body from -1 (left edge) to 1 (right edge)
+// Everything mapped through it bends with the chain
+pub struct BodyMap {
+    centers: Vec<Vector>,
+    // Unit vectors pointing to the right side of every circle
+    sides: Vec<Vector>,
+    radii: Vec<f32>,
+    // Distance along the spine of every circle center
+    lengths: Vec<f32>,
+}
+
+impl BodyMap {
+    pub fn new(chain: &Chain) -> Self {
+        let centers: Vec<Vector> = chain.circles.iter().map(|c| c.position).collect();
+        let mut lengths = vec![0.0];
+        for pair in centers.windows(2) {
+            let last = lengths[lengths.len() - 1];
+            lengths.push(last + Chain::vector_length(pair[1] - pair[0]));
+        }
+        Self {
+            sides: chain
+                .circles
+                .iter()
+                .map(|c| Chain::rotate_vector(c.direction, 3.0 * PI / 2.0))
+                .collect(),
+            radii: chain.circles.iter().map(|c| c.radius).collect(),
+            centers,
+            lengths,
+        }
+    }
+
+    // Length of the spine, from the head center to the tail center
+    pub fn length(&self) -> f32 {
+        self.lengths[self.lengths.len() - 1]
+    }
+
+    // Distance along the spine of every circle center
+    pub fn circle_lengths(&self) -> &[f32] {
+        &self.lengths
+    }
+
+    // Circle before the point at distance u and how far the point is towards the next circle
+    fn segment(&self, u: f32) -> (usize, f32) {
+        let u = u.clamp(0.0, self.length());
+        let i = self
+            .lengths
+            .partition_point(|length| *length <= u)
+            .clamp(1, self.lengths.len() - 1)
+            - 1;
+        let span = self.lengths[i + 1] - self.lengths[i];
+        let t = if span > f32::EPSILON {
+            (u - self.lengths[i]) / span
+        } else {
+            0.0
+        };
+        (i, t)
+    }
+
+    // Half width of the body at distance u
+    pub fn radius(&self, u: f32) -> f32 {
+        let (i, t) = self.segment(u);
+        self.radii[i] + (self.radii[i + 1] - self.radii[i]) * t
+    }
+
+    // World position of a body coordinate
+    pub fn point(&self, u: f32, v: f32) -> Vector {
+        let (i, t) = self.segment(u);
+        let center = self.centers[i] + (self.centers[i + 1] - self.centers[i]) * t;
+        let mut side = self.sides[i] + (self.sides[i + 1] - self.sides[i]) * t;
+        let length = Chain::vector_length(side);
+        if length > f32::EPSILON {
+            side = side * (1.0 / length);
+        }
+        center + side * (v * self.radius(u))
+    }
+
+    // Path of a polygon given in body coordinates, long edges are split so they follow the bends
+    pub fn polygon(&self, corners: &[(f32, f32)], builder: &mut Builder) {
+        const SAMPLE: f32 = 6.0;
+        let Some(&(u, v)) = corners.first() else {
+            return;
+        };
+        builder.move_to(Point::ORIGIN + self.point(u, v));
+        for (k, &(u0, v0)) in corners.iter().enumerate() {
+            let (u1, v1) = corners[(k + 1) % corners.len()];
+            let steps = ((u1 - u0).abs() / SAMPLE).ceil().max(1.0) as usize;
+            for step in 1..=steps {
+                let t = step as f32 / steps as f32;
+                builder.line_to(Point::ORIGIN + self.point(u0 + (u1 - u0) * t, v0 + (v1 - v0) * t));
+            }
+        }
+        builder.close();
+    }
+}
+
+// Pattern painted over the body color, sizes are in pixels along the spine
+// and widths are fractions of the body width
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum Pattern {
+    #[default]
+    Plain,
+    // Bands across the body
+    Stripes {
+        spacing: f32,
+        width: f32,
+    },
+    // Diamonds along the back
+    Diamonds {
+        spacing: f32,
+        length: f32,
+        width: f32,
+    },
+    // Bands running along the body on both sides, offset from the spine
+    Bands {
+        offset: f32,
+        width: f32,
+    },
+    // Alternating scales, in rows across the body
+    Checker {
+        length: f32,
+        rows: u32,
+    },
+}
+
+impl Pattern {
+    // Patterns stay slightly inside the edges, so the outline stroke isn't covered
+    const INSET: f32 = 0.9;
+
+    // Cycle through the patterns with their default sizes
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Plain => Self::Stripes {
+                spacing: 40.0,
+                width: 14.0,
+            },
+            Self::Stripes { .. } => Self::Diamonds {
+                spacing: 45.0,
+                length: 30.0,
+                width: 0.5,
+            },
+            Self::Diamonds { .. } => Self::Bands {
+                offset: 0.5,
+                width: 0.25,
+            },
+            Self::Bands { .. } => Self::Checker {
+                length: 16.0,
+                rows: 4,
+            },
+            Self::Checker { .. } => Self::Plain,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        match *self {
+            Self::Plain => Ok(()),
+            Self::Stripes { spacing, width } if !positive(spacing) || !positive(width) => {
+                Err(String::from("stripes spacing and width must be positive"))
+            }
+            Self::Diamonds {
+                spacing,
+                length,
+                width,
+            } if !positive(spacing) || !positive(length) || !positive(width) => Err(String::from(
+                "diamonds spacing, length and width must be positive",
+            )),
+            Self::Bands { offset, width } if !offset.is_finite() || !positive(width) => Err(
+                String::from("bands width must be positive and offset a finite number"),
+            ),
+            Self::Checker { length, rows } if !positive(length) || rows == 0 => {
+                Err(String::from("checker length and rows must be positive"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Shapes of the pattern on a body, None for a plain body
+    pub fn path(&self, map: &BodyMap) -> Option<Path> {
+        let length = map.length();
+        let shapes: Vec<Vec<(f32, f32)>> = match *self {
+            Self::Plain => return None,
+            Self::Stripes { spacing, width } => Self::repeat(spacing, length)
+                .map(|u| vec![(u, -1.0), (u + width, -1.0), (u + width, 1.0), (u, 1.0)])
+                .collect(),
+            Self::Diamonds {
+                spacing,
+                length: size,
+                width,
+            } => Self::repeat(spacing, length)
+                .map(|u| {
+                    vec![
+                        (u - size / 2.0, 0.0),
+                        (u, -width),
+                        (u + size / 2.0, 0.0),
+                        (u, width),
+                    ]
+                })
+                .collect(),
+            Self::Bands { offset, width } => {
+                let band = |v: f32| {
+                    vec![
+                        (0.0, v - width / 2.0),
+                        (length, v - width / 2.0),
+                        (length, v + width / 2.0),
+                        (0.0, v + width / 2.0),
+                    ]
+                };
+                match offset.abs() > width / 2.0 {
+                    true => vec![band(-offset), band(offset)],
+                    false => vec![band(0.0)],
+                }
+            }
+            Self::Checker { length: size, rows } => {
+                let height = 2.0 / rows as f32;
+                (0..(length / size).ceil() as usize)
+                    .flat_map(|k| (0..rows as usize).map(move |row| (k, row)))
+                    .filter(|(k, row)| (k + row) % 2 == 0)
+                    .map(|(k, row)| {
+                        let u = k as f32 * size;
+                        let v = -1.0 + row as f32 * height;
+                        vec![
+                            (u, v),
+                            (u + size, v),
+                            (u + size, v + height),
+                            (u, v + height),
+                        ]
+                    })
+                    .collect()
+            }
+        };
+        Some(Path::new(|builder| {
+            for shape in shapes.iter() {
+                let shape: Vec<(f32, f32)> =
+                    shape.iter().map(|(u, v)| (*u, v * Self::INSET)).collect();
+                map.polygon(&shape, builder);
+            }
+        }))
+    }
+
+    // Positions along the spine of repeated shapes, starting half a spacing after the head
+    fn repeat(spacing: f32, length: f32) -> impl Iterator<Item = f32> {
+        let count = (length / spacing).max(0.0) as usize;
+        (0..count).map(move |k| (k as f32 + 0.5) * spacing)
+    }
+}
+
+// Colors spread along the body, from head to tail
+pub struct ColorRamp {
+    // Distance along the spine and color of every stop, in order
+    stops: Vec<(f32, Color)>,
+}
+
+impl ColorRamp {
+    // One color per circle puts every color on its circle, other counts are spread evenly over the spine
+    pub fn new(colors: &[Color], map: &BodyMap) -> Self {
+        let lengths = map.circle_lengths();
+        let stops = match colors.len() {
+            0 => vec![(0.0, Color::BLACK)],
+            1 => vec![(0.0, colors[0])],
+            n if n == lengths.len() => lengths
+                .iter()
+                .copied()
+                .zip(colors.iter().copied())
+                .collect(),
+            n => colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| (map.length() * i as f32 / (n - 1) as f32, *color))
+                .collect(),
+        };
+        Self { stops }
+    }
+
+    // Color at a distance along the spine, blended between the two closest stops
+    pub fn at(&self, u: f32) -> Color {
+        let next = self.stops.partition_point(|(length, _)| *length <= u);
+        if next == 0 {
+            return self.stops[0].1;
+        }
+        if next == self.stops.len() {
+            return self.stops[next - 1].1;
+        }
+        let (u0, a) = self.stops[next - 1];
+        let (u1, b) = self.stops[next];
+        let t = if u1 - u0 > f32::EPSILON {
+            (u - u0) / (u1 - u0)
+        } else {
+            0.0
+        };
+        Color {
+            r: a.r + (b.r - a.r) * t,
+            g: a.g + (b.g - a.g) * t,
+            b: a.b + (b.b - a.b) * t,
+            a: a.a + (b.a - a.a) * t,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Straight spine with circles 10 pixels apart, 20 pixels long
+    fn map() -> BodyMap {
+        let chain = Chain::new()
+            .circles_radii(vec![10.0, 10.0, 10.0])
+            .circles_positions(|i: usize, r: f32| (Some(i as f32 * r), Some(0.0)))
+            .default_outline()
+            .build();
+        BodyMap::new(&chain)
+    }
+
+    fn close(a: Color, b: Color) -> bool {
+        [a.r - b.r, a.g - b.g, a.b - b.b, a.a - b.a]
+            .iter()
+            .all(|d| d.abs() < 1e-5)
+    }
+
+    #[test]
+    fn one_color_is_used_everywhere() {
+        let ramp = ColorRamp::new(&[Color::WHITE], &map());
+        for u in [-5.0, 0.0, 7.5, 20.0, 100.0] {
+            assert_eq!(ramp.at(u), Color::WHITE);
+        }
+    }
+
+    #[test]
+    fn one_color_per_circle_sits_on_its_circle() {
+        let colors = [Color::BLACK, Color::WHITE, Color::from_rgb(1.0, 0.0, 0.0)];
+        let ramp = ColorRamp::new(&colors, &map());
+        assert_eq!(ramp.at(0.0), Color::BLACK);
+        assert_eq!(ramp.at(10.0), Color::WHITE);
+        assert_eq!(ramp.at(20.0), colors[2]);
+        assert!(close(ramp.at(5.0), Color::from_rgb(0.5, 0.5, 0.5)));
+        assert!(close(ramp.at(15.0), Color::from_rgb(1.0, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn other_counts_are_spread_evenly_and_clamped_at_the_ends() {
+        let ramp = ColorRamp::new(&[Color::BLACK, Color::WHITE], &map());
+        assert_eq!(ramp.at(-10.0), Color::BLACK);
+        assert!(close(ramp.at(5.0), Color::from_rgb(0.25, 0.25, 0.25)));
+        assert!(close(ramp.at(10.0), Color::from_rgb(0.5, 0.5, 0.5)));
+        assert_eq!(ramp.at(50.0), Color::WHITE);
+    }
+}
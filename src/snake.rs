@@ -1,29 +1,157 @@
+use crate::appendage::*;
 use crate::chain::*;
+use crate::contour::*;
+use crate::overlay::*;
+use crate::palette::*;
+use crate::replay::*;
+use crate::rng::*;
+use crate::skin::*;
+use crate::stroke::*;
+use crate::trail::*;
+use crate::world::*;
+use clap::ValueEnum;
 use iced::{
-    widget::canvas::{Frame, Path, Stroke},
-    Color, Point, Vector,
+    widget::canvas::{path::arc::Elliptical, Frame, LineCap, Path, Stroke, Text},
+    Color, Point, Radians, Vector,
 };
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
+// The drawing chains are not saved, call sync_display after loading a snake
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Snake {
+    // Simulated chain, advanced by fixed time steps
     pub chain: Chain,
+    // Chain at the previous time step, used for interpolation
+    #[serde(skip)]
+    previous: Chain,
+    // Chain interpolated between the last two steps, used for drawing
+    #[serde(skip)]
+    pub display: Chain,
+    // Contour points of the display chain, refilled whenever it changes so drawing doesn't recompute them
+    #[serde(skip)]
+    outline: Vec<Vector>,
+    #[serde(with = "crate::persist::vector")]
     pub destination: Vector,
+    // Destination set by the user, used instead of picking a random one
+    #[serde(with = "crate::persist::option_vector")]
+    pub pinned: Option<Vector>,
     pub vision_angle: f32,
+    // Speeds are in pixels per second
     pub speed: f32,
-    min_speed: f32,
-    max_speed: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    #[serde(with = "crate::persist::color")]
     pub color: Color,
+    #[serde(with = "crate::persist::color")]
+    pub eye_color: Color,
+    // Colors spread from head to tail, one per circle or evenly spaced, the body uses color if empty
+    #[serde(with = "crate::persist::colors", default)]
+    pub colors: Vec<Color>,
+    // Color of the sides of the body, the back keeps the body colors
+    #[serde(with = "crate::persist::option_color", default)]
+    pub belly_color: Option<Color>,
+    // Pattern painted over the body color
+    #[serde(default)]
+    pub pattern: Pattern,
+    #[serde(
+        with = "crate::persist::color",
+        default = "Snake::default_pattern_color"
+    )]
+    pub pattern_color: Color,
+    // Lines and bands inside the outline, e.g. a dorsal stripe or belly plates
+    #[serde(default)]
+    pub contours: Vec<Contour>,
+    // Width and colors of the stroke around the body, separately for the head
+    #[serde(default)]
+    pub outline_stroke: OutlineStroke,
+    pub eyes: Vec<Eye>,
+    // Color of the fins, spikes and antennae, the body color if missing
+    #[serde(with = "crate::persist::option_color", default)]
+    pub appendage_color: Option<Color>,
+    #[serde(with = "crate::persist::color", default = "Snake::default_pupil_color")]
+    pub pupil_color: Color,
+    // What the pupils look at
+    #[serde(default)]
+    pub gaze: Gaze,
+    // Blink every few seconds
+    #[serde(default = "Snake::default_blinking")]
+    pub blinking: bool,
+    // Track of recent positions drawn behind the creature
+    #[serde(default)]
+    pub trail: Option<Trail>,
+    // Seconds until the next blink, the eyes close and open again while it goes from 0 to -BLINK_TIME
+    #[serde(default)]
+    blink: f32,
+    // Blinking has its own generator, so it doesn't change where the snake goes
+    #[serde(default)]
+    blink_rng: SimRng,
     action: FsmAction,
     tail_size: FsmTailSize,
     tail_shake: FsmTailShake,
-    turn_angle: f32,
+    // Radians per second
+    pub turn_rate: f32,
+    // Pulse and shake the tail
+    pub animate_tail: bool,
+    // Every snake has its own generator, so adding a snake doesn't change the others
+    rng: SimRng,
+}
+
+// Eye attached to one of the circles of the chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Eye {
+    pub circle: usize,
+    // Angle from the direction of the circle, in radians
+    pub angle: f32,
+    // Distance from the center of the circle, as a fraction of its radius
+    pub distance: f32,
+    pub radius: f32,
+    // Radius of the pupil, no pupil if 0 and scaled to the eye if missing
+    #[serde(
+        with = "crate::persist::bare_option",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pupil: Option<f32>,
+}
+
+impl Eye {
+    // Radius of the pupil as a fraction of the eye radius, when it isn't given
+    pub const PUPIL_RATIO: f32 = 0.5;
+
+    // The given pupil, or one scaled to the eye
+    pub fn pupil_radius(&self) -> f32 {
+        self.pupil.unwrap_or(self.radius * Self::PUPIL_RATIO)
+    }
+}
+
+// What the pupils of a snake follow
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Gaze {
+    #[default]
+    Destination,
+    // The cursor when it is over the canvas, the destination otherwise
+    Cursor,
+}
+
+// Body shapes a snake can be spawned with
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum, Serialize, Deserialize)]
+pub enum Preset {
+    #[default]
+    Slick,
+    Chunky,
+    // Short body with flapping fins and a tail fin
+    Fish,
+    // Segmented body with antennae and legs
+    Bug,
 }
 
 // Enum for the actions of the move automaton
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum FsmAction {
     GoStraight,
-    Forward(u32),
+    Forward(f32),
     TurnLeft,
     TurnRight,
     Target,
@@ -35,17 +163,19 @@ enum FsmAction {
     Reach,
 }
 
-// Enum for pulsating the tail, the integer represents how many frames we've been in this action
+// Enum for pulsating the tail, the float represents how many seconds are left in this action
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum FsmTailSize {
-    Normal(u32),
-    Shrink(u32),
-    Grow(u32),
+    Normal(f32),
+    Shrink(f32),
+    Grow(f32),
 }
 
-// Enum for moving the tail, the integer represents how many frames we've been in this action
+// Enum for moving the tail, the float represents how many seconds are left in this action
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum FsmTailShake {
-    Left(u32),
-    Right(u32),
+    Left(f32),
+    Right(f32),
 }
 
 // If the action is temporary we move to another action in the same frame
@@ -70,42 +200,156 @@ impl FsmAction {
 
 #[allow(dead_code)]
 impl Snake {
-    pub fn new() -> Self {
-        let mut chain = Self::slick_chain();
+    // Spawn a new snake with its head at a random point of the world
+    pub fn new(world: &World, preset: Preset, mut rng: SimRng) -> Self {
+        let chain = match preset {
+            Preset::Slick => Self::slick_chain(&mut rng),
+            Preset::Chunky => Self::chunky_chain(&mut rng),
+            Preset::Fish => Self::fish_chain(&mut rng),
+            Preset::Bug => Self::bug_chain(&mut rng),
+        };
+        Self::with_chain(chain, world, rng)
+    }
+
+    // Spawn a snake with the given body and the default looks and behavior
+    pub fn with_chain(mut chain: Chain, world: &World, mut rng: SimRng) -> Self {
         chain.update_positions(0);
+        chain.translate(world.random_point(&mut rng) - chain.circles[0].position);
+        let mut blink_rng = SimRng::new(rng.state() ^ 0xB1B1_B1B1_B1B1_B1B1);
+        let blink = blink_rng.gen_range(Self::BLINK_INTERVAL);
         let destination = chain.circles[0].position;
         Snake {
+            previous: chain.clone(),
+            display: chain.clone(),
+            outline: chain.outline_points(),
             chain,
             destination,
+            pinned: None,
             vision_angle: PI / 6.0,
-            speed: 3.0,
-            min_speed: 3.0,
-            max_speed: 12.0,
+            speed: 90.0,
+            min_speed: 90.0,
+            max_speed: 360.0,
             color: Color::from_rgb8(168, 58, 50),
+            eye_color: Color::WHITE,
+            colors: Vec::new(),
+            belly_color: None,
+            pattern: Pattern::Plain,
+            pattern_color: Self::default_pattern_color(),
+            eyes: vec![
+                Eye {
+                    circle: 1,
+                    angle: -PI * 0.2,
+                    distance: 0.9,
+                    radius: 6.0,
+                    pupil: None,
+                },
+                Eye {
+                    circle: 1,
+                    angle: PI * 0.2,
+                    distance: 0.9,
+                    radius: 6.0,
+                    pupil: None,
+                },
+            ],
+            appendage_color: None,
+            contours: Vec::new(),
+            outline_stroke: OutlineStroke::default(),
+            pupil_color: Self::default_pupil_color(),
+            gaze: Gaze::Destination,
+            blinking: true,
+            trail: None,
+            blink,
+            blink_rng,
             action: FsmAction::Reach,
-            tail_size: FsmTailSize::Normal(30),
-            tail_shake: FsmTailShake::Left(5),
-            turn_angle: PI / 100.0,
+            tail_size: FsmTailSize::Normal(1.0),
+            tail_shake: FsmTailShake::Left(1.0 / 6.0),
+            turn_rate: PI * 0.3,
+            animate_tail: false,
+            rng,
         }
     }
 
-    fn chunky_chain() -> Chain {
+    fn default_pupil_color() -> Color {
+        Color::from_rgb8(20, 20, 24)
+    }
+
+    fn default_blinking() -> bool {
+        true
+    }
+
+    fn default_pattern_color() -> Color {
+        Color::from_rgb8(92, 26, 22)
+    }
+
+    // Random vertical positions for the circles before they get bound together
+    fn scattered_heights(rng: &mut SimRng, count: usize) -> Vec<f32> {
+        (0..count).map(|_| rng.gen_range(-300.0..300.0)).collect()
+    }
+
+    fn chunky_chain(rng: &mut SimRng) -> Chain {
+        let heights = Self::scattered_heights(rng, 9);
         Chain::new()
             .circles_radii(vec![30.0, 48.0, 70.0, 60.5, 40.0, 30.5, 20.0, 20.0, 25.5])
             .circles_offsets(vec![0.0, 0.0, 13.0, -20.2, -10.0, -10.0, 15.0, 30.0, 0.0])
             .circles_positions(|i: usize, r: f32| {
-                use rand::Rng;
-                let mut rng = rand::thread_rng();
-                (
-                    Some(i as f32 * r * 3.0 + 100.0),
-                    Some(rng.gen_range(-300.0..300.0)),
-                )
+                (Some(i as f32 * r * 3.0 + 100.0), Some(heights[i]))
             })
             .default_outline()
             .build()
     }
 
-    fn slick_chain() -> Chain {
+    fn fish_chain(rng: &mut SimRng) -> Chain {
+        let heights = Self::scattered_heights(rng, 8);
+        Chain::new()
+            .circles_radii(vec![22.0, 28.0, 27.0, 23.0, 18.0, 13.0, 9.0, 6.0])
+            .circles_offsets(vec![0.0, 0.0, -6.0, -6.0, -4.0, -2.0, 0.0, 0.0])
+            .circles_positions(|i: usize, r: f32| {
+                (Some(i as f32 * r * 3.0 + 100.0), Some(heights[i]))
+            })
+            .default_outline()
+            .appendages(vec![
+                Appendage::fin(1, PI * 0.45, 30.0, 16.0),
+                Appendage::fin(1, PI * 1.55, 30.0, 16.0),
+                Appendage::fin(4, PI * 0.55, 16.0, 10.0),
+                Appendage::fin(4, PI * 1.45, 16.0, 10.0),
+                Appendage::new(
+                    7,
+                    PI,
+                    AppendageKind::Fin {
+                        length: 34.0,
+                        width: 10.0,
+                        rake: 0.0,
+                        flap: PI / 6.0,
+                    },
+                ),
+            ])
+            .build()
+    }
+
+    fn bug_chain(rng: &mut SimRng) -> Chain {
+        let heights = Self::scattered_heights(rng, 6);
+        Chain::new()
+            .circles_radii(vec![14.0, 17.0, 22.0, 26.0, 22.0, 15.0])
+            .circles_offsets(vec![0.0, -4.0, -4.0, -6.0, -6.0, -4.0])
+            .circles_positions(|i: usize, r: f32| {
+                (Some(i as f32 * r * 3.0 + 100.0), Some(heights[i]))
+            })
+            .default_outline()
+            .appendages(vec![
+                Appendage::antenna(0, PI * 0.12, 45.0),
+                Appendage::antenna(0, PI * 1.88, 45.0),
+                Appendage::spike(2, PI * 0.5, 22.0, 6.0),
+                Appendage::spike(2, PI * 1.5, 22.0, 6.0),
+                Appendage::spike(3, PI * 0.5, 26.0, 6.0),
+                Appendage::spike(3, PI * 1.5, 26.0, 6.0),
+                Appendage::spike(4, PI * 0.5, 22.0, 6.0),
+                Appendage::spike(4, PI * 1.5, 22.0, 6.0),
+            ])
+            .build()
+    }
+
+    fn slick_chain(rng: &mut SimRng) -> Chain {
+        let heights = Self::scattered_heights(rng, 53);
         Chain::new()
             .circles_radii(
                 vec![30.0, 35.0, 28.0]
@@ -126,12 +370,7 @@ impl Snake {
                     .collect(),
             )
             .circles_positions(|i: usize, r: f32| {
-                use rand::Rng;
-                let mut rng = rand::thread_rng();
-                (
-                    Some(i as f32 * r * 10.0 + 800.0),
-                    Some(rng.gen_range(-300.0..300.0)),
-                )
+                (Some(i as f32 * r * 10.0 + 800.0), Some(heights[i]))
             })
             .default_outline()
             .refine_outline(
@@ -155,8 +394,21 @@ impl Snake {
             .build()
     }
 
-    // Function to transition between FSM actions
-    pub fn transition(&mut self) {
+    // Acceleration while going straight and deceleration while turning, in pixels per second squared
+    const ACCELERATION: f32 = 90.0;
+    const DECELERATION: f32 = 45.0;
+
+    // Seconds the eyes take to close and open again, and seconds between two blinks
+    const BLINK_TIME: f32 = 0.15;
+    const BLINK_INTERVAL: std::ops::Range<f32> = 2.0..6.0;
+
+    // Radius of the circles the head can't reach by turning at max speed, with some margin
+    pub fn turning_radius(&self) -> f32 {
+        self.max_speed / (2.0 * self.turn_rate) + 50.0
+    }
+
+    // Function to transition between FSM actions, dt is the length of the time step in seconds
+    pub fn transition(&mut self, dt: f32) {
         self.action = match self.action {
             FsmAction::Target => FsmAction::Look,
             FsmAction::Look => {
@@ -171,8 +423,7 @@ impl Snake {
                 }
             }
             FsmAction::Spiral => {
-                // Number of vertexes for spiral regular polygon
-                let radius = self.max_speed / (2.0 * self.turn_angle.sin()) + 50.0;
+                let radius = self.turning_radius();
                 let center_left = self.chain.circles[0].position
                     + Chain::rotate_vector(self.chain.circles[0].direction, -PI / 2.0)
                         * radius
@@ -185,16 +436,16 @@ impl Snake {
                 if Chain::vector_length(self.destination - center_left) < radius
                     || Chain::vector_length(self.destination - center_right) < radius
                 {
-                    FsmAction::Forward(30)
+                    FsmAction::Forward(1.0)
                 } else {
                     FsmAction::Orient
                 }
             }
-            FsmAction::Forward(i) => {
-                if i == 0 {
+            FsmAction::Forward(t) => {
+                if t <= 0.0 {
                     FsmAction::Orient
                 } else {
-                    FsmAction::Forward(i - 1)
+                    FsmAction::Forward(t - dt)
                 }
             }
             FsmAction::Orient => {
@@ -244,6 +495,92 @@ impl Snake {
         }
     }
 
+    // Head for a new destination right away, the FSM starts over by looking for it
+    pub fn set_destination(&mut self, destination: Vector) {
+        self.destination = destination;
+        self.action = FsmAction::Target;
+    }
+
+    // Keep following a point until unpinned, without interrupting the current action
+    pub fn pin_destination(&mut self, destination: Option<Vector>) {
+        self.pinned = destination;
+        if let Some(destination) = destination {
+            self.destination = destination;
+        }
+    }
+
+    // Keep the snake and its destination inside the world after a resize
+    pub fn fit_to_world(&mut self, world: &World) {
+        world.keep_inside(&mut self.chain);
+        self.destination = world.clamp_inner_point(self.destination);
+        self.sync_display();
+    }
+
+    // Swap the body for a new one, e.g. after its definition file changed
+    pub fn replace_chain(&mut self, mut chain: Chain) {
+        chain.update_positions(0);
+        self.chain = chain;
+        self.tail_size = FsmTailSize::Normal(1.0);
+        self.sync_display();
+    }
+
+    // Feed the simulated state into a checksum, the drawing chains and looks are left out
+    pub fn hash_state(&self, hash: &mut StateHash) {
+        for circle in self.chain.circles.iter() {
+            hash.write_f32(circle.radius);
+            hash.write_f32(circle.offset);
+            hash.write_vector(circle.position);
+            hash.write_vector(circle.direction);
+        }
+        hash.write_vector(self.destination);
+        match self.pinned {
+            Some(pinned) => {
+                hash.write_u64(1);
+                hash.write_vector(pinned);
+            }
+            None => hash.write_u64(0),
+        }
+        for value in [
+            self.vision_angle,
+            self.speed,
+            self.min_speed,
+            self.max_speed,
+            self.turn_rate,
+        ] {
+            hash.write_f32(value);
+        }
+        // Debug output of floats round trips exactly, so it is enough for the FSM state
+        hash.write_bytes(
+            format!(
+                "{:?} {:?} {:?} {}",
+                self.action, self.tail_size, self.tail_shake, self.animate_tail
+            )
+            .as_bytes(),
+        );
+        hash.write_u64(self.rng.state());
+    }
+
+    // Forget the previous step, used when the chain is changed outside of the simulation
+    pub fn sync_display(&mut self) {
+        self.previous.clone_from(&self.chain);
+        self.display.clone_from(&self.chain);
+        self.update_display_points();
+    }
+
+    // Blend the last two steps for drawing, alpha goes from 0 (previous step) to 1 (current step)
+    pub fn interpolate(&mut self, alpha: f32) {
+        self.display.interpolate(&self.previous, &self.chain, alpha);
+        self.update_display_points();
+    }
+
+    // Points of the outline and the contours on the drawn chain, in buffers kept between frames
+    fn update_display_points(&mut self) {
+        self.display.outline_points_into(&mut self.outline);
+        for contour in self.contours.iter_mut() {
+            contour.update(&self.display);
+        }
+    }
+
     // Function to increase/decrease speed, makes sure we stay inside [min_speed:max_speed]
     pub fn modify_speed(&mut self, acceleration: f32) {
         self.speed += acceleration;
@@ -252,102 +589,99 @@ impl Snake {
     }
 
     // Function to move the snake depending on the FSM action
-    pub fn move_action(&mut self) {
+    pub fn move_action(&mut self, dt: f32) {
         match self.action {
             FsmAction::GoStraight => {
                 // Head straight for the point once the target is in the field of vision
                 self.chain.circles[0].set_target(self.destination);
-                self.modify_speed(0.1);
+                self.modify_speed(Self::ACCELERATION * dt);
             }
             FsmAction::TurnLeft => {
                 self.chain.circles[0].direction =
-                    Chain::rotate_vector(self.chain.circles[0].direction, -self.turn_angle);
-                self.modify_speed(-0.05);
+                    Chain::rotate_vector(self.chain.circles[0].direction, -self.turn_rate * dt);
+                self.modify_speed(-Self::DECELERATION * dt);
             }
             FsmAction::TurnRight => {
                 self.chain.circles[0].direction =
-                    Chain::rotate_vector(self.chain.circles[0].direction, self.turn_angle);
-                self.modify_speed(-0.05);
+                    Chain::rotate_vector(self.chain.circles[0].direction, self.turn_rate * dt);
+                self.modify_speed(-Self::DECELERATION * dt);
             }
             FsmAction::Forward(_) => {
-                self.modify_speed(0.1);
+                self.modify_speed(Self::ACCELERATION * dt);
             }
             _ => {}
         }
     }
 
     // Function to perform an extra action depending on the FSM action
-    pub fn extra_action(&mut self) {
-        match self.action {
-            FsmAction::Target => {
-                // Set the snake's destination to a random point
-                use rand::Rng;
-                let mut rng = rand::thread_rng();
-                self.destination =
-                    Vector::new(rng.gen_range(-400.0..400.0), rng.gen_range(-300.0..300.0));
-            }
-            _ => {}
+    pub fn extra_action(&mut self, world: &World) {
+        if let FsmAction::Target = self.action {
+            // Set the snake's destination to the pinned point or to a random point inside the world
+            self.destination = match self.pinned {
+                Some(pinned) => pinned,
+                None => world.random_point(&mut self.rng),
+            };
         }
     }
 
     // FSM Transition function for tail size enum
-    pub fn tail_size_transition(&mut self) {
+    pub fn tail_size_transition(&mut self, dt: f32) {
         self.tail_size = match self.tail_size {
-            FsmTailSize::Normal(i) => {
-                if i == 0 {
-                    FsmTailSize::Grow(15)
+            FsmTailSize::Normal(t) => {
+                if t <= 0.0 {
+                    FsmTailSize::Grow(0.5)
                 } else {
-                    FsmTailSize::Normal(i - 1)
+                    FsmTailSize::Normal(t - dt)
                 }
             }
-            FsmTailSize::Grow(i) => {
-                if i == 0 {
-                    FsmTailSize::Shrink(15)
+            FsmTailSize::Grow(t) => {
+                if t <= 0.0 {
+                    FsmTailSize::Shrink(0.5)
                 } else {
-                    FsmTailSize::Grow(i - 1)
+                    FsmTailSize::Grow(t - dt)
                 }
             }
-            FsmTailSize::Shrink(i) => {
-                if i == 0 {
-                    FsmTailSize::Normal(30)
+            FsmTailSize::Shrink(t) => {
+                if t <= 0.0 {
+                    FsmTailSize::Normal(1.0)
                 } else {
-                    FsmTailSize::Shrink(i - 1)
+                    FsmTailSize::Shrink(t - dt)
                 }
             }
         }
     }
 
-    // Increasing/Decreasing the tail size
-    pub fn tail_size_move(&mut self) {
+    // Increasing/Decreasing the tail size, in pixels per second
+    pub fn tail_size_move(&mut self, dt: f32) {
         let size = self.chain.circles.len() - 1;
         match self.tail_size {
             FsmTailSize::Grow(_) => {
-                self.chain.circles[size].radius += 0.4;
-                self.chain.circles[size - 1].radius += 0.2;
+                self.chain.circles[size].radius += 12.0 * dt;
+                self.chain.circles[size - 1].radius += 6.0 * dt;
             }
             FsmTailSize::Shrink(_) => {
-                self.chain.circles[size].radius -= 0.4;
-                self.chain.circles[size - 1].radius -= 0.2;
+                self.chain.circles[size].radius -= 12.0 * dt;
+                self.chain.circles[size - 1].radius -= 6.0 * dt;
             }
             FsmTailSize::Normal(_) => {}
         }
     }
 
     // FSM Transition function for tail shake enum
-    pub fn tail_shake_transition(&mut self) {
+    pub fn tail_shake_transition(&mut self, dt: f32) {
         self.tail_shake = match self.tail_shake {
-            FsmTailShake::Left(i) => {
-                if i == 0 {
-                    FsmTailShake::Right(10)
+            FsmTailShake::Left(t) => {
+                if t <= 0.0 {
+                    FsmTailShake::Right(1.0 / 3.0)
                 } else {
-                    FsmTailShake::Left(i - 1)
+                    FsmTailShake::Left(t - dt)
                 }
             }
-            FsmTailShake::Right(i) => {
-                if i == 0 {
-                    FsmTailShake::Left(10)
+            FsmTailShake::Right(t) => {
+                if t <= 0.0 {
+                    FsmTailShake::Left(1.0 / 3.0)
                 } else {
-                    FsmTailShake::Right(i - 1)
+                    FsmTailShake::Right(t - dt)
                 }
             }
         }
@@ -372,11 +706,13 @@ impl Snake {
         }
     }
 
-    pub fn update(&mut self) {
+    // Advance the simulation by dt seconds
+    pub fn update(&mut self, world: &World, dt: f32) {
+        self.previous.clone_from(&self.chain);
         loop {
-            self.transition();
-            self.extra_action();
-            self.move_action();
+            self.transition(dt);
+            self.extra_action(world);
+            self.move_action(dt);
             if !self.action.is_temporary() {
                 break;
             }
@@ -384,97 +720,387 @@ impl Snake {
         // Move the chain in the direction it's pointing
         self.chain.circles[0].normalize_direction();
         self.chain.circles[0].position =
-            self.chain.circles[0].position + self.chain.circles[0].direction * self.speed;
-        /*
-        self.tail_size_transition();
-        self.tail_size_move();
+            self.chain.circles[0].position + self.chain.circles[0].direction * self.speed * dt;
+        world.constrain(&mut self.chain);
 
-        self.tail_shake_transition();
-        self.tail_shake_move();
-        */
+        if self.animate_tail {
+            self.tail_size_transition(dt);
+            self.tail_size_move(dt);
+
+            self.tail_shake_transition(dt);
+            self.tail_shake_move();
+        }
 
         self.chain.update_positions(0);
+        self.chain.update_appendages(self.turn_speed(dt), dt);
+        if let Some(trail) = self.trail.as_mut() {
+            trail.record(&self.chain);
+        }
+        self.blink(dt);
+    }
+
+    // Signed turn rate of the head during the last step, in radians per second
+    fn turn_speed(&self, dt: f32) -> f32 {
+        let Some(previous) = self.previous.circles.first() else {
+            return 0.0;
+        };
+        let (a, b) = (previous.direction, self.chain.circles[0].direction);
+        (a.x * b.y - a.y * b.x).atan2(a.x * b.x + a.y * b.y) / dt
     }
 
-    pub fn draw(&self, frame: &mut Frame) {
+    // Count down to the next blink, picking a new random interval after every blink
+    fn blink(&mut self, dt: f32) {
+        if !self.blinking {
+            self.blink = self.blink.max(0.0);
+            return;
+        }
+        self.blink -= dt;
+        if self.blink <= -Self::BLINK_TIME {
+            self.blink = self.blink_rng.gen_range(Self::BLINK_INTERVAL);
+        }
+    }
+
+    // How open the eyes are, from 0 (closed) to 1 (open)
+    pub fn eye_openness(&self) -> f32 {
+        if self.blink >= 0.0 {
+            return 1.0;
+        }
+        (-self.blink / Self::BLINK_TIME * 2.0 - 1.0).abs().min(1.0)
+    }
+
+    // The frame is already transformed by the camera, so the world origin is Point::ORIGIN
+    // cursor is the world position of the cursor, followed by the pupils if the gaze asks for it
+    pub fn draw(&self, frame: &mut Frame, cursor: Option<Vector>, palette: &Palette) {
         // Draw the target
         frame.fill(
-            &Path::circle(frame.center() + self.destination, 5.0),
-            Color::from_rgb8(252, 50, 145),
+            &Path::circle(Point::ORIGIN + self.destination, 5.0),
+            palette.target,
         );
 
-        // self.draw_circles(frame);
-        self.draw_outline(frame);
-
-        frame.fill(&self.eyes_path(frame.center()), Color::WHITE);
+        if let Some(trail) = &self.trail {
+            let head = self.display.circles.get(trail.circle).map(|c| c.position);
+            trail.draw(frame, head, self.color);
+        }
+        self.draw_appendages(frame, palette);
+        self.draw_outline(frame, palette);
+        self.draw_eyes(frame, cursor);
+    }
 
-        // self.show_blind_spots(frame);
+    // Debug views drawn on top of every creature
+    pub fn draw_overlays(&self, frame: &mut Frame, overlays: &Overlays, palette: &Palette) {
+        if overlays.circles {
+            self.draw_circles(frame, palette);
+        }
+        if overlays.blind_spots {
+            self.show_blind_spots(frame, palette);
+        }
+        if overlays.vision_cone {
+            self.draw_vision_cone(frame, palette);
+        }
+        if overlays.directions {
+            self.draw_directions(frame, palette);
+        }
+        self.draw_centers(frame, overlays.centers, palette);
+        if overlays.outline_points {
+            self.draw_outline_points(frame, palette);
+        }
+        if overlays.action {
+            self.draw_action(frame, palette);
+        }
     }
 
-    pub fn draw_circles(&self, frame: &mut Frame) {
-        frame.fill(&self.chain.circle_path(frame.center()), self.color);
+    pub fn draw_circles(&self, frame: &mut Frame, palette: &Palette) {
+        // Snake color
+        frame.fill(&self.display.circle_path(Point::ORIGIN), self.color);
         // Snake stroke
         frame.stroke(
-            &self.chain.circle_path(frame.center()),
+            &self.display.circle_path(Point::ORIGIN),
             Stroke {
-                style: Color::WHITE.into(),
+                style: palette.outline.into(),
                 width: 1.0,
                 ..Default::default()
             },
         );
-        // Snake color
     }
 
-    pub fn draw_outline(&self, frame: &mut Frame) {
-        // Snake stroke
+    // Drawn before the body, so the roots of the appendages are hidden under it
+    pub fn draw_appendages(&self, frame: &mut Frame, palette: &Palette) {
+        if self.display.appendages.is_empty() {
+            return;
+        }
+        let color = self.appendage_color.unwrap_or(self.color);
+        let shapes = self.display.appendages_path(Point::ORIGIN);
         frame.stroke(
-            &self.chain.outline_path(frame.center()),
+            &shapes,
             Stroke {
-                style: Color::from_rgba8(255, 255, 255, 1.0).into(),
-                width: 4.0,
+                style: palette.outline.into(),
+                width: 3.0,
                 ..Default::default()
             },
         );
+        frame.fill(&shapes, color);
+        frame.stroke(
+            &self.display.antennae_path(Point::ORIGIN),
+            Stroke {
+                style: color.into(),
+                width: 2.0,
+                line_cap: LineCap::Round,
+                ..Default::default()
+            },
+        );
+    }
+
+    // Contour of the display chain, as computed by the last interpolate
+    pub fn outline(&self) -> &[Vector] {
+        &self.outline
+    }
+
+    pub fn draw_outline(&self, frame: &mut Frame, palette: &Palette) {
+        // The same path is stroked and filled
+        let outline = Chain::polygon_path(&self.outline, Point::ORIGIN);
+        // Snake stroke
+        self.outline_stroke.draw(
+            frame,
+            &self.display,
+            &self.outline,
+            &outline,
+            palette.outline,
+        );
         // Snake color
-        frame.fill(&self.chain.outline_path(frame.center()), self.color);
+        if self.colors.is_empty() && self.belly_color.is_none() {
+            frame.fill(&outline, self.color);
+        } else {
+            self.draw_gradient(frame, &outline);
+        }
+        self.draw_pattern(frame);
+        self.draw_contours(frame);
     }
 
-    // Function for drawing the snake's eyes
-    pub fn eyes_path(&self, frame_center: Point) -> Path {
-        Path::new(|builder| {
-            builder.circle(
-                frame_center
-                    + self.chain.circles[1].position
-                    + Chain::rotate_vector(self.chain.circles[1].direction, -PI * 0.2)
-                        * self.chain.circles[1].radius
-                        * 0.9,
-                6.0,
-            );
-            builder.circle(
-                frame_center
-                    + self.chain.circles[1].position
-                    + Chain::rotate_vector(self.chain.circles[1].direction, PI * 0.2)
-                        * self.chain.circles[1].radius
-                        * 0.9,
-                6.0,
+    // Paint the body in thin slices across the spine, so the colors follow the bends
+    pub fn draw_gradient(&self, frame: &mut Frame, outline: &Path) {
+        // Length of a slice along the spine and the part of the width covered by the back
+        const SLICE: f32 = 8.0;
+        const BACK: f32 = 0.45;
+        let map = BodyMap::new(&self.display);
+        let colors = match self.colors.is_empty() {
+            true => vec![self.color],
+            false => self.colors.clone(),
+        };
+        let ramp = ColorRamp::new(&colors, &map);
+        let length = map.length();
+
+        // The head and tail caps reach past the spine, they get the colors of its ends
+        frame.fill(outline, ramp.at(0.0));
+        let tail = self
+            .display
+            .circle_outline_points(self.display.circles.len() - 1);
+        if tail.len() >= 3 {
+            let cap = Path::new(|builder| {
+                builder.move_to(Point::ORIGIN + tail[0]);
+                for point in tail.iter().skip(1) {
+                    builder.line_to(Point::ORIGIN + *point);
+                }
+                builder.close();
+            });
+            frame.fill(&cap, ramp.at(length));
+        }
+
+        let (side, back) = match self.belly_color {
+            Some(belly) => {
+                let body = [(0.0, -1.0), (length, -1.0), (length, 1.0), (0.0, 1.0)];
+                frame.fill(&Path::new(|builder| map.polygon(&body, builder)), belly);
+                (BACK, BACK)
+            }
+            None => (1.0, 1.0),
+        };
+        let slices = (length / SLICE).ceil() as usize;
+        for k in 0..slices {
+            let u0 = k as f32 * SLICE;
+            // Slices overlap a little, so no seams show between them
+            let u1 = (u0 + SLICE + 0.5).min(length);
+            let slice = [(u0, -side), (u1, -side), (u1, back), (u0, back)];
+            frame.fill(
+                &Path::new(|builder| map.polygon(&slice, builder)),
+                ramp.at(u0 + SLICE / 2.0),
             );
+        }
+    }
+
+    // Pattern mapped onto the body, so it bends along with the chain
+    pub fn draw_pattern(&self, frame: &mut Frame) {
+        if let Some(path) = self.pattern.path(&BodyMap::new(&self.display)) {
+            frame.fill(&path, self.pattern_color);
+        }
+    }
+
+    pub fn draw_contours(&self, frame: &mut Frame) {
+        for contour in self.contours.iter() {
+            contour.draw(frame);
+        }
+    }
+
+    // Center of an eye and the unit vector from the center of its circle towards it
+    fn eye_position(&self, eye: &Eye) -> Option<(Vector, Vector)> {
+        let circle = self.display.circles.get(eye.circle)?;
+        let outward = Chain::rotate_vector(circle.direction, eye.angle);
+        Some((
+            circle.position + outward * circle.radius * eye.distance,
+            outward,
+        ))
+    }
+
+    pub fn draw_eyes(&self, frame: &mut Frame, cursor: Option<Vector>) {
+        let openness = self.eye_openness();
+        frame.fill(&self.eyes_path(Point::ORIGIN, openness), self.eye_color);
+        // The pupils disappear behind the eyelids while blinking
+        if openness > 0.5 {
+            let target = match (self.gaze, cursor) {
+                (Gaze::Cursor, Some(cursor)) => cursor,
+                _ => self.destination,
+            };
+            frame.fill(&self.pupils_path(Point::ORIGIN, target), self.pupil_color);
+        }
+    }
+
+    // Function for drawing the snake's eyes, squashed towards the body while blinking
+    pub fn eyes_path(&self, frame_center: Point, openness: f32) -> Path {
+        Path::new(|builder| {
+            for eye in self.eyes.iter() {
+                let Some((center, outward)) = self.eye_position(eye) else {
+                    continue;
+                };
+                builder.ellipse(Elliptical {
+                    center: frame_center + center,
+                    radii: Vector::new(eye.radius, (eye.radius * openness).max(0.5)),
+                    rotation: Radians(outward.y.atan2(outward.x) + PI / 2.0),
+                    start_angle: Radians(0.0),
+                    end_angle: Radians(2.0 * PI),
+                });
+            }
             builder.close();
         })
     }
 
-    pub fn show_blind_spots(&self, frame: &mut Frame) {
-        let radius = self.max_speed / (2.0 * self.turn_angle.sin()) + 50.0;
-        let center_left = self.chain.circles[0].position
-            + Chain::rotate_vector(self.chain.circles[0].direction, -PI / 2.0) * radius * 0.8;
-        let center_right = self.chain.circles[0].position
-            + Chain::rotate_vector(self.chain.circles[0].direction, PI / 2.0) * radius * 0.8;
+    // Pupils moved towards the target, as far as they fit inside the eyes
+    pub fn pupils_path(&self, frame_center: Point, target: Vector) -> Path {
+        Path::new(|builder| {
+            for eye in self.eyes.iter().filter(|eye| eye.pupil_radius() > 0.0) {
+                let pupil = eye.pupil_radius();
+                let Some((center, _)) = self.eye_position(eye) else {
+                    continue;
+                };
+                let look = target - center;
+                let distance = Chain::vector_length(look);
+                let reach = (eye.radius - pupil).max(0.0);
+                let offset = if distance > f32::EPSILON {
+                    look * (reach.min(distance) / distance)
+                } else {
+                    Vector::new(0.0, 0.0)
+                };
+                builder.circle(frame_center + center + offset, pupil);
+            }
+        })
+    }
+
+    pub fn show_blind_spots(&self, frame: &mut Frame, palette: &Palette) {
+        let radius = self.turning_radius();
+        let center_left = self.display.circles[0].position
+            + Chain::rotate_vector(self.display.circles[0].direction, -PI / 2.0) * radius * 0.8;
+        let center_right = self.display.circles[0].position
+            + Chain::rotate_vector(self.display.circles[0].direction, PI / 2.0) * radius * 0.8;
         frame.fill(
-            &Path::circle(frame.center() + center_left, radius),
-            Color::from_rgba8(255, 255, 255, 0.2),
+            &Path::circle(Point::ORIGIN + center_left, radius),
+            palette.blind_spots,
         );
         frame.fill(
-            &Path::circle(frame.center() + center_right, radius),
-            Color::from_rgba8(255, 255, 255, 0.2),
+            &Path::circle(Point::ORIGIN + center_right, radius),
+            palette.blind_spots,
+        );
+    }
+
+    // Draw the center markers, either all of them or only the circles with show_center
+    pub fn draw_centers(&self, frame: &mut Frame, all: bool, palette: &Palette) {
+        for circle in self.display.circles.iter() {
+            if all || circle.show_center {
+                frame.fill(&circle.center_path(Point::ORIGIN), palette.centers);
+            }
+        }
+    }
+
+    // Draw the direction vector of each circle, as long as its radius
+    pub fn draw_directions(&self, frame: &mut Frame, palette: &Palette) {
+        let path = Path::new(|builder| {
+            for circle in self.display.circles.iter() {
+                builder.move_to(Point::ORIGIN + circle.position);
+                builder.line_to(Point::ORIGIN + circle.point_on_circle(circle.direction));
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke {
+                style: palette.directions.into(),
+                width: 1.5,
+                ..Default::default()
+            },
+        );
+    }
+
+    // Draw the points of the outline together with their index
+    pub fn draw_outline_points(&self, frame: &mut Frame, palette: &Palette) {
+        for (i, point) in self.outline.iter().copied().enumerate() {
+            frame.fill(
+                &Path::circle(Point::ORIGIN + point, 2.5),
+                palette.outline_points,
+            );
+            frame.fill_text(Text {
+                content: i.to_string(),
+                position: Point::ORIGIN + point + Vector::new(3.0, 3.0),
+                color: palette.outline_points,
+                size: 10.0.into(),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Draw the field of vision used to decide between going straight and spiraling
+    pub fn draw_vision_cone(&self, frame: &mut Frame, palette: &Palette) {
+        let head = &self.display.circles[0];
+        let length = head.radius * 8.0;
+        let path = Path::new(|builder| {
+            builder.move_to(Point::ORIGIN + head.position);
+            // Approximate the arc of the cone with short segments
+            let steps = 16;
+            for step in 0..=steps {
+                let angle =
+                    -self.vision_angle + 2.0 * self.vision_angle * step as f32 / steps as f32;
+                builder.line_to(
+                    Point::ORIGIN
+                        + head.position
+                        + Chain::rotate_vector(head.direction, angle) * length,
+                );
+            }
+            builder.close();
+        });
+        frame.fill(&path, palette.vision_cone);
+        frame.stroke(
+            &path,
+            Stroke {
+                style: palette.overlay_line.into(),
+                width: 1.0,
+                ..Default::default()
+            },
         );
     }
+
+    // Write the current FSM action next to the head
+    pub fn draw_action(&self, frame: &mut Frame, palette: &Palette) {
+        let head = &self.display.circles[0];
+        frame.fill_text(Text {
+            content: format!("{:?}", self.action),
+            position: Point::ORIGIN + head.position + Vector::new(head.radius, -head.radius),
+            color: palette.foreground,
+            size: 16.0.into(),
+            ..Default::default()
+        });
+    }
 }
@@ -0,0 +1,343 @@
+use crate::chain::*;
+use crate::skin::*;
+use iced::{
+    widget::canvas::{Frame, LineCap, LineJoin, Path, Stroke},
+    Color, Point, Vector,
+};
+use serde::{Deserialize, Serialize};
+
+// How the width of a stroke changes along the outline, in pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StrokeWidth {
+    Constant(f32),
+    // From the head to the tail
+    Taper { head: f32, tail: f32 },
+    // From min where the spine is straight to max where it bends into a tight curve
+    Curvature { min: f32, max: f32 },
+}
+
+impl StrokeWidth {
+    // Radius of the spine's curve, in pixels, at which a Curvature stroke reaches its full width
+    const FULL_BEND_RADIUS: f32 = 60.0;
+
+    // Width at a fraction t of the spine, where the spine turns by bend radians per pixel
+    fn at(&self, t: f32, bend: f32) -> f32 {
+        match *self {
+            Self::Constant(width) => width,
+            Self::Taper { head, tail } => head + (tail - head) * t,
+            Self::Curvature { min, max } => {
+                min + (max - min) * (bend * Self::FULL_BEND_RADIUS).min(1.0)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid = |width: f32| width.is_finite() && width >= 0.0;
+        let ok = match *self {
+            Self::Constant(width) => valid(width),
+            Self::Taper { head, tail } => valid(head) && valid(tail),
+            Self::Curvature { min, max } => valid(min) && valid(max),
+        };
+        match ok {
+            true => Ok(()),
+            false => Err(String::from("stroke widths must be at least 0")),
+        }
+    }
+}
+
+// Width and colors of one part of the outline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrokeStyle {
+    pub width: StrokeWidth,
+    // Colors from head to tail, spread like the body gradient, the palette outline color if empty
+    #[serde(with = "crate::persist::colors", default)]
+    pub colors: Vec<Color>,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: StrokeWidth::Constant(4.0),
+            colors: Vec::new(),
+        }
+    }
+}
+
+// Stroke around the body, the outline points of the first circles use the head style
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutlineStroke {
+    pub head_circles: usize,
+    pub head: StrokeStyle,
+    pub body: StrokeStyle,
+}
+
+impl Default for OutlineStroke {
+    fn default() -> Self {
+        Self {
+            head_circles: 1,
+            head: StrokeStyle::default(),
+            body: StrokeStyle::default(),
+        }
+    }
+}
+
+impl OutlineStroke {
+    // Widths are rounded to this many pixels and positions along the spine to this many steps,
+    // edges that end up with the same width and color are stroked together
+    const WIDTH_STEP: f32 = 0.5;
+    const STEPS: f32 = 24.0;
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.head
+            .width
+            .validate()
+            .map_err(|message| format!("head: {}", message))?;
+        self.body
+            .width
+            .validate()
+            .map_err(|message| format!("body: {}", message))
+    }
+
+    // One width and one color everywhere, the outline can be stroked as a single path
+    fn uniform(&self) -> Option<f32> {
+        match (&self.head.width, &self.body.width) {
+            (StrokeWidth::Constant(head), StrokeWidth::Constant(body))
+                if head == body && self.head.colors.is_empty() && self.body.colors.is_empty() =>
+            {
+                Some(*body)
+            }
+            _ => None,
+        }
+    }
+
+    // points and outline are the contour of the chain and its path, color is used for the styles without colors
+    pub fn draw(
+        &self,
+        frame: &mut Frame,
+        chain: &Chain,
+        points: &[Vector],
+        outline: &Path,
+        color: Color,
+    ) {
+        if let Some(width) = self.uniform() {
+            frame.stroke(
+                outline,
+                Stroke {
+                    style: color.into(),
+                    width,
+                    ..Default::default()
+                },
+            );
+            return;
+        }
+        self.runs(chain, points, color, |run, look| {
+            Self::stroke_run(frame, run, look)
+        });
+    }
+
+    // Calls f with every run of edges sharing a width (in steps of WIDTH_STEP) and a color
+    fn runs(
+        &self,
+        chain: &Chain,
+        points: &[Vector],
+        color: Color,
+        mut f: impl FnMut(&[Vector], (u32, [u8; 4])),
+    ) {
+        let circles: Vec<usize> = chain.outline_circles().collect();
+        let n = points.len();
+        if n < 2 || circles.len() != n {
+            return;
+        }
+        let map = BodyMap::new(chain);
+        let lengths = map.circle_lengths();
+        let length = map.length().max(f32::EPSILON);
+        let ramp = |style: &StrokeStyle| match style.colors.is_empty() {
+            true => ColorRamp::new(&[color], &map),
+            false => ColorRamp::new(&style.colors, &map),
+        };
+        let (head_ramp, body_ramp) = (ramp(&self.head), ramp(&self.body));
+        let bends = Self::bends(chain);
+
+        // Width and color of every edge, rounded so that neighbouring edges usually share them
+        let look = |i: usize| {
+            let j = (i + 1) % n;
+            let head = circles[i] < self.head_circles && circles[j] < self.head_circles;
+            let (style, ramp) = match head {
+                true => (&self.head, &head_ramp),
+                false => (&self.body, &body_ramp),
+            };
+            let t = (lengths[circles[i]] + lengths[circles[j]]) / (2.0 * length);
+            let t = (t * Self::STEPS).round() / Self::STEPS;
+            let bend = (bends[circles[i]] + bends[circles[j]]) / 2.0;
+            let width = (style.width.at(t, bend) / Self::WIDTH_STEP).round() as u32;
+            (width, ramp.at(t * length).into_rgba8())
+        };
+
+        // Edges with the same look are joined into one path, so there are a few paths per outline
+        let mut run: Vec<Vector> = Vec::with_capacity(n + 1);
+        let mut current = look(0);
+        run.push(points[0]);
+        for i in 0..n {
+            let next = look(i);
+            if next != current {
+                f(&run, current);
+                run.clear();
+                run.push(points[i]);
+                current = next;
+            }
+            run.push(points[(i + 1) % n]);
+        }
+        f(&run, current);
+    }
+
+    // Edges of the same look stroked at once, width in steps of WIDTH_STEP
+    fn stroke_run(frame: &mut Frame, run: &[Vector], (width, [r, g, b, a]): (u32, [u8; 4])) {
+        if width == 0 || run.len() < 2 {
+            return;
+        }
+        let color = Color::from_rgba8(r, g, b, a as f32 / 255.0);
+        let path = Path::new(|builder| {
+            builder.move_to(Point::ORIGIN + run[0]);
+            for point in run.iter().skip(1) {
+                builder.line_to(Point::ORIGIN + *point);
+            }
+        });
+        frame.stroke(
+            &path,
+            Stroke {
+                style: color.into(),
+                width: width as f32 * Self::WIDTH_STEP,
+                // Round caps hide the joints between runs, but would darken translucent colors where they overlap
+                line_cap: match a {
+                    255 => LineCap::Round,
+                    _ => LineCap::Butt,
+                },
+                line_join: LineJoin::Round,
+                ..Default::default()
+            },
+        );
+    }
+
+    // How much the spine bends at every circle, in radians per pixel, the ends take the bend next to them
+    fn bends(chain: &Chain) -> Vec<f32> {
+        let centers: Vec<Vector> = chain.circles.iter().map(|c| c.position).collect();
+        let mut bends = vec![0.0; centers.len()];
+        for i in 1..centers.len().saturating_sub(1) {
+            let a = centers[i] - centers[i - 1];
+            let b = centers[i + 1] - centers[i];
+            let span = (Chain::vector_length(a) + Chain::vector_length(b)) / 2.0;
+            if span > f32::EPSILON {
+                let turn = (a.x * b.y - a.y * b.x).atan2(a.x * b.x + a.y * b.y).abs();
+                bends[i] = turn / span;
+            }
+        }
+        if bends.len() > 2 {
+            let last = bends.len() - 1;
+            bends[0] = bends[1];
+            bends[last] = bends[last - 1];
+        }
+        bends
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    // Chain as long as the slick preset, straight or bent along a circle, with a head cap sampled every 45 degrees
+    fn chain(bent: bool) -> Chain {
+        let radii: Vec<f32> = (0..53).map(|i| 30.0 - i as f32 * 0.5).collect();
+        let mut chain = Chain::new()
+            .circles_radii(radii)
+            .circles_positions(|i: usize, _| match bent {
+                true => {
+                    let angle = i as f32 * 0.06;
+                    (Some(400.0 * angle.cos()), Some(400.0 * angle.sin()))
+                }
+                false => (Some(i as f32 * 24.0), Some(0.0)),
+            })
+            .default_outline()
+            .refine_outline(vec![(0..8).map(|k| k as f32 * PI / 4.0).collect()])
+            .build();
+        for i in 1..chain.circles.len() {
+            let direction = chain.circles[i - 1].position - chain.circles[i].position;
+            chain.circles[i].direction = direction * (1.0 / Chain::vector_length(direction));
+        }
+        chain.circles[0].direction = chain.circles[1].direction;
+        chain
+    }
+
+    fn runs(stroke: &OutlineStroke, chain: &Chain) -> Vec<(u32, [u8; 4], usize)> {
+        let mut points = Vec::new();
+        chain.outline_points_into(&mut points);
+        let mut runs = Vec::new();
+        stroke.runs(chain, &points, Color::WHITE, |run, (width, color)| {
+            runs.push((width, color, run.len() - 1))
+        });
+        runs
+    }
+
+    fn style(width: StrokeWidth) -> StrokeStyle {
+        StrokeStyle {
+            width,
+            colors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn every_edge_is_in_one_run() {
+        let chain = chain(true);
+        let stroke = OutlineStroke {
+            body: style(StrokeWidth::Taper {
+                head: 6.0,
+                tail: 1.0,
+            }),
+            ..OutlineStroke::default()
+        };
+        let edges: usize = runs(&stroke, &chain).iter().map(|run| run.2).sum();
+        assert_eq!(edges, chain.outline_circles().count());
+    }
+
+    #[test]
+    fn edges_are_batched_into_few_paths() {
+        let chain = chain(true);
+        let stroke = OutlineStroke {
+            head: style(StrokeWidth::Constant(8.0)),
+            body: StrokeStyle {
+                width: StrokeWidth::Taper {
+                    head: 6.0,
+                    tail: 1.0,
+                },
+                colors: vec![Color::BLACK, Color::WHITE],
+            },
+            head_circles: 1,
+        };
+        let runs = runs(&stroke, &chain);
+        let edges: usize = runs.iter().map(|run| run.2).sum();
+        // Bounded by the rounding steps, not by the number of edges
+        assert!(
+            runs.len() <= 2 * (OutlineStroke::STEPS as usize + 2),
+            "{} runs for {} edges",
+            runs.len(),
+            edges
+        );
+        assert!(runs.len() * 2 <= edges);
+        // Thick at the head, thin at the tail
+        assert_eq!(runs[0].0, 16);
+        let widths: Vec<u32> = runs.iter().map(|run| run.0).collect();
+        assert!(widths.contains(&2));
+    }
+
+    #[test]
+    fn curvature_follows_the_spine_not_the_head_cap() {
+        let stroke = OutlineStroke {
+            head: style(StrokeWidth::Curvature { min: 2.0, max: 8.0 }),
+            body: style(StrokeWidth::Curvature { min: 2.0, max: 8.0 }),
+            head_circles: 1,
+        };
+        let widest = |chain: &Chain| runs(&stroke, chain).iter().map(|run| run.0).max();
+        assert_eq!(widest(&chain(false)), Some(4));
+        assert!(widest(&chain(true)) > Some(4));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::chain::*;
+use iced::{
+    widget::canvas::{Frame, LineCap, LineJoin, Path, Stroke},
+    Color, Point, Vector,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// How the recorded positions are drawn, sizes in pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TrailStyle {
+    // Line through every position, thinning towards its end
+    Ribbon { width: f32 },
+    // Dot at every few steps, so the spacing shows the speed
+    Dots { every: usize, radius: f32 },
+}
+
+// Positions of one circle over the last simulation steps, fading with age
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trail {
+    pub circle: usize,
+    // Number of steps kept
+    pub length: usize,
+    pub style: TrailStyle,
+    // Color of the newest part, the body color of the creature if missing
+    #[serde(with = "crate::persist::option_color", default)]
+    pub color: Option<Color>,
+    // Oldest position first
+    #[serde(skip)]
+    points: VecDeque<Vector>,
+    // Number of positions recorded since the trail was made, dropped ones included
+    #[serde(skip)]
+    count: usize,
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self::new(0, 90, TrailStyle::Ribbon { width: 6.0 }, None)
+    }
+}
+
+impl Trail {
+    // Alpha of the newest part of the trail
+    const OPACITY: f32 = 0.6;
+    // The trail is drawn with this many alpha steps, one path each
+    const FADE_STEPS: usize = 8;
+    // Positions further apart than this are not joined, e.g. after wrapping around the world
+    const MAX_GAP: f32 = 100.0;
+
+    pub fn new(circle: usize, length: usize, style: TrailStyle, color: Option<Color>) -> Self {
+        Self {
+            circle,
+            length,
+            style,
+            color,
+            points: VecDeque::with_capacity(length),
+            count: 0,
+        }
+    }
+
+    // Cycle through no trail, a ribbon and dots, keeping the circle, length and color
+    pub fn next(trail: &Option<Trail>) -> Option<Trail> {
+        match trail {
+            None => Some(Trail::default()),
+            Some(trail) => match trail.style {
+                TrailStyle::Ribbon { .. } => Some(Trail::new(
+                    trail.circle,
+                    trail.length,
+                    TrailStyle::Dots {
+                        every: 4,
+                        radius: 3.0,
+                    },
+                    trail.color,
+                )),
+                TrailStyle::Dots { .. } => None,
+            },
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        if self.length < 2 {
+            return Err(String::from("trail length must be at least 2 steps"));
+        }
+        match self.style {
+            TrailStyle::Ribbon { width } if !positive(width) => {
+                Err(String::from("trail ribbon width must be positive"))
+            }
+            TrailStyle::Dots { every, radius } if every == 0 || !positive(radius) => {
+                Err(String::from("trail dots every and radius must be positive"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Add the position of the circle after a simulation step, forgetting the oldest one
+    pub fn record(&mut self, chain: &Chain) {
+        let Some(circle) = chain.circles.get(self.circle) else {
+            return;
+        };
+        if self.points.len() >= self.length {
+            self.points.pop_front();
+        }
+        self.points.push_back(circle.position);
+        self.count += 1;
+    }
+
+    // head is where the circle is drawn right now, between two steps
+    pub fn draw(&self, frame: &mut Frame, head: Option<Vector>, color: Color) {
+        let color = self.color.unwrap_or(color);
+        let recorded = self.points.len();
+        let n = recorded + head.iter().count();
+        if n < 2 {
+            return;
+        }
+        let at = |i: usize| match (i < recorded, head) {
+            (false, Some(head)) => head,
+            _ => self.points[i],
+        };
+        // Oldest parts first, every fade step gets a more opaque color and a bigger size
+        for step in 0..Self::FADE_STEPS {
+            let start = n * step / Self::FADE_STEPS;
+            let stop = n * (step + 1) / Self::FADE_STEPS;
+            let age = (step + 1) as f32 / Self::FADE_STEPS as f32;
+            let color = Color {
+                a: color.a * Self::OPACITY * age,
+                ..color
+            };
+            match self.style {
+                TrailStyle::Ribbon { width } => {
+                    // Every part reaches the first point of the next one, so there are no holes
+                    let end = stop.min(n - 1);
+                    if start >= end {
+                        continue;
+                    }
+                    let path = Path::new(|builder| {
+                        builder.move_to(Point::ORIGIN + at(start));
+                        for i in start + 1..=end {
+                            match Chain::vector_length(at(i) - at(i - 1)) > Self::MAX_GAP {
+                                true => builder.move_to(Point::ORIGIN + at(i)),
+                                false => builder.line_to(Point::ORIGIN + at(i)),
+                            }
+                        }
+                    });
+                    frame.stroke(
+                        &path,
+                        Stroke {
+                            style: color.into(),
+                            width: width * age,
+                            line_cap: LineCap::Round,
+                            line_join: LineJoin::Round,
+                            ..Default::default()
+                        },
+                    );
+                }
+                TrailStyle::Dots { every, radius } => {
+                    // Every position keeps its number, so the dots stay where they were dropped
+                    let first = self.count - recorded;
+                    let path = Path::new(|builder| {
+                        let dots = (start..stop.min(recorded))
+                            .filter(|i| (first + i).is_multiple_of(every.max(1)));
+                        for i in dots {
+                            builder.circle(Point::ORIGIN + at(i), radius * age.sqrt());
+                        }
+                    });
+                    frame.fill(&path, color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+// Polls a local directory and reports the RON files that were created or modified since the last poll
+pub struct DirWatcher {
+    directory: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl DirWatcher {
+    pub fn new(directory: &Path) -> Self {
+        // Canonical paths, so the reported files can be compared with other canonical paths
+        let directory = fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+        let mut watcher = Self {
+            directory,
+            modified: HashMap::new(),
+        };
+        // The files that are already there don't count as changes
+        watcher.poll();
+        watcher
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // Return the files that changed, a directory that can't be read has no changes
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        let mut changed = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "ron") {
+                continue;
+            }
+            let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) else {
+                continue;
+            };
+            if self.modified.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,245 @@
+use crate::chain::*;
+use iced::{Size, Vector};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// What happens to a creature's head once it reaches the edge of the world
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EdgePolicy {
+    // The whole chain is moved to the opposite edge
+    Wrap,
+    // The head direction is mirrored off the edge
+    Bounce,
+    // The head is gradually turned back towards the inside of the world
+    SteerBack,
+    // The head is stopped at the edge
+    Clamp,
+}
+
+impl EdgePolicy {
+    // Cycle through the policies
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Wrap => Self::Bounce,
+            Self::Bounce => Self::SteerBack,
+            Self::SteerBack => Self::Clamp,
+            Self::Clamp => Self::Wrap,
+        }
+    }
+}
+
+// The world is a rectangle centered in the origin, the same origin used for the chain positions
+#[derive(Serialize, Deserialize)]
+pub struct World {
+    pub half_width: f32,
+    pub half_height: f32,
+    // Distance from the edges where random destinations are not picked and where SteerBack starts turning
+    pub margin: f32,
+    pub policy: EdgePolicy,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self {
+            half_width: 400.0,
+            half_height: 300.0,
+            margin: 50.0,
+            policy: EdgePolicy::SteerBack,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl World {
+    // Fit the world to the size of the canvas
+    pub fn resize(&mut self, size: Size) {
+        self.half_width = size.width / 2.0;
+        self.half_height = size.height / 2.0;
+    }
+
+    // Check if a point is inside the world
+    pub fn contains(&self, point: Vector) -> bool {
+        point.x.abs() <= self.half_width && point.y.abs() <= self.half_height
+    }
+
+    // Pick a random point inside the world, away from the edges by margin
+    pub fn random_point(&self, rng: &mut impl Rng) -> Vector {
+        let x = (self.half_width - self.margin).max(1.0);
+        let y = (self.half_height - self.margin).max(1.0);
+        Vector::new(rng.gen_range(-x..x), rng.gen_range(-y..y))
+    }
+
+    // Move a point inside the world
+    pub fn clamp_point(&self, point: Vector) -> Vector {
+        Vector::new(
+            point.x.clamp(-self.half_width, self.half_width),
+            point.y.clamp(-self.half_height, self.half_height),
+        )
+    }
+
+    // Move a point inside the world, away from the edges by margin
+    pub fn clamp_inner_point(&self, point: Vector) -> Vector {
+        let x = (self.half_width - self.margin).max(0.0);
+        let y = (self.half_height - self.margin).max(0.0);
+        Vector::new(point.x.clamp(-x, x), point.y.clamp(-y, y))
+    }
+
+    // Bring a chain back on screen after the world got smaller, without changing its shape
+    pub fn keep_inside(&self, chain: &mut Chain) {
+        let head = chain.circles[0].position;
+        if !self.contains(head) {
+            chain.translate(self.clamp_inner_point(head) - head);
+        }
+    }
+
+    // Apply the edge policy to the head of the chain, should be called after the head has moved
+    pub fn constrain(&self, chain: &mut Chain) {
+        let head = &mut chain.circles[0];
+        match self.policy {
+            EdgePolicy::Wrap => {
+                // Translate the whole body so the chain doesn't get stretched across the world
+                let mut shift = Vector::new(0.0, 0.0);
+                if head.position.x > self.half_width {
+                    shift.x = -2.0 * self.half_width;
+                } else if head.position.x < -self.half_width {
+                    shift.x = 2.0 * self.half_width;
+                }
+                if head.position.y > self.half_height {
+                    shift.y = -2.0 * self.half_height;
+                } else if head.position.y < -self.half_height {
+                    shift.y = 2.0 * self.half_height;
+                }
+                if shift.x != 0.0 || shift.y != 0.0 {
+                    chain.translate(shift);
+                }
+            }
+            EdgePolicy::Bounce => {
+                // Only mirror the direction if the head is still moving outwards
+                if head.position.x.abs() > self.half_width
+                    && head.position.x * head.direction.x > 0.0
+                {
+                    head.direction.x = -head.direction.x;
+                }
+                if head.position.y.abs() > self.half_height
+                    && head.position.y * head.direction.y > 0.0
+                {
+                    head.direction.y = -head.direction.y;
+                }
+                head.position = self.clamp_point(head.position);
+            }
+            EdgePolicy::SteerBack => {
+                // The further the head goes past the margin, the stronger it gets pulled back
+                let inner_x = (self.half_width - self.margin).max(0.0);
+                let inner_y = (self.half_height - self.margin).max(0.0);
+                let pull = Vector::new(
+                    Self::overshoot(head.position.x, inner_x),
+                    Self::overshoot(head.position.y, inner_y),
+                ) * (-1.0 / self.margin.max(1.0));
+                if pull.x != 0.0 || pull.y != 0.0 {
+                    head.normalize_direction();
+                    head.direction = head.direction + pull * 0.1;
+                }
+            }
+            EdgePolicy::Clamp => {
+                head.position = self.clamp_point(head.position);
+            }
+        }
+    }
+
+    // How far a coordinate is past [-limit:limit], negative on the lower side
+    fn overshoot(value: f32, limit: f32) -> f32 {
+        if value > limit {
+            value - limit
+        } else if value < -limit {
+            value + limit
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(policy: EdgePolicy) -> World {
+        World {
+            policy,
+            ..World::default()
+        }
+    }
+
+    // Chain lying along the x axis with its head at x, facing left
+    fn chain(x: f32, y: f32) -> Chain {
+        Chain::new()
+            .circles_radii(vec![10.0, 10.0, 10.0])
+            .circles_positions(|i: usize, r: f32| (Some(x + i as f32 * r), Some(y)))
+            .default_outline()
+            .build()
+    }
+
+    fn positions(chain: &Chain) -> Vec<Vector> {
+        chain.circles.iter().map(|c| c.position).collect()
+    }
+
+    #[test]
+    fn constrain_leaves_heads_inside_alone() {
+        for policy in [
+            EdgePolicy::Wrap,
+            EdgePolicy::Bounce,
+            EdgePolicy::SteerBack,
+            EdgePolicy::Clamp,
+        ] {
+            let mut inside = chain(0.0, 0.0);
+            world(policy).constrain(&mut inside);
+            assert_eq!(positions(&inside), positions(&chain(0.0, 0.0)));
+            assert_eq!(inside.circles[0].direction, Vector::new(-1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn wrap_moves_the_whole_chain() {
+        let mut wrapped = chain(-410.0, 310.0);
+        world(EdgePolicy::Wrap).constrain(&mut wrapped);
+        let shift = Vector::new(800.0, -600.0);
+        let expected: Vec<Vector> = positions(&chain(-410.0, 310.0))
+            .into_iter()
+            .map(|p| p + shift)
+            .collect();
+        assert_eq!(positions(&wrapped), expected);
+    }
+
+    #[test]
+    fn bounce_mirrors_outward_directions_only() {
+        let world = world(EdgePolicy::Bounce);
+        let mut outward = chain(-410.0, 0.0);
+        world.constrain(&mut outward);
+        assert_eq!(outward.circles[0].direction, Vector::new(1.0, 0.0));
+        assert_eq!(outward.circles[0].position, Vector::new(-400.0, 0.0));
+
+        let mut inward = chain(-410.0, 0.0);
+        inward.circles[0].direction = Vector::new(1.0, 0.0);
+        world.constrain(&mut inward);
+        assert_eq!(inward.circles[0].direction, Vector::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn steer_back_turns_towards_the_inside() {
+        let mut outside = chain(0.0, 290.0);
+        outside.circles[0].direction = Vector::new(0.0, 1.0);
+        world(EdgePolicy::SteerBack).constrain(&mut outside);
+        let direction = outside.circles[0].direction;
+        assert!(direction.y < 1.0);
+        assert_eq!(direction.x, 0.0);
+        // Steering only changes the direction, the head keeps its position
+        assert_eq!(outside.circles[0].position, Vector::new(0.0, 290.0));
+    }
+
+    #[test]
+    fn clamp_stops_the_head_at_the_edge() {
+        let mut outside = chain(500.0, -350.0);
+        world(EdgePolicy::Clamp).constrain(&mut outside);
+        assert_eq!(outside.circles[0].position, Vector::new(400.0, -300.0));
+        assert_eq!(outside.circles[1].position, Vector::new(510.0, -350.0));
+    }
+}